@@ -63,3 +63,52 @@ fn bench_dec_chg_long(b: &mut Bencher) {
             %20pariatur.Excepteur%20sint%20occaecat%20cupidatat%20non%20proident%20sunt%20in%20culpa%20qui%20officia%20deserunt%20mollit%20anim%20id%20est%20laborum.")
     });
 }
+
+#[bench]
+fn bench_enc_mixed(b: &mut Bencher) {
+    let keys = [
+        "photos/2024/IMG_0001 (copy).jpg", "users/42/avatar.png", "logs/app-server-01/2024-05-17T10:00:00Z.log.gz",
+        "reports/Q1 summary & forecast.pdf", "cache/ab/cd/ef0123456789abcdef0123456789abcdef.bin", "ünïcödé/名前.txt",
+    ];
+    b.bytes = keys.iter().map(|k| k.len() as u64).sum();
+    b.iter(|| {
+        for key in keys {
+            test::black_box(encode(test::black_box(key)));
+        }
+    });
+}
+
+#[bench]
+fn bench_enc_nop_large(b: &mut Bencher) {
+    let data = "Lorem-ipsum-dolor-sit-amet.consectetur_adipisicing~elit-".repeat(1000);
+    b.bytes = data.len() as u64;
+    b.iter(|| encode(test::black_box(&data)).len());
+}
+
+#[bench]
+fn bench_enc_chg_large(b: &mut Bencher) {
+    let data = "Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt. ".repeat(1000);
+    b.bytes = data.len() as u64;
+    b.iter(|| encode(test::black_box(&data)).len());
+}
+
+#[bench]
+fn bench_dec_nop_large(b: &mut Bencher) {
+    let data = "Lorem-ipsum-dolor-sit-amet.consectetur_adipisicing~elit-".repeat(1000);
+    b.bytes = data.len() as u64;
+    b.iter(|| decode(test::black_box(&data)).unwrap().len());
+}
+
+#[bench]
+fn bench_dec_chg_large(b: &mut Bencher) {
+    let data = encode("Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt. ").repeat(1000);
+    b.bytes = data.len() as u64;
+    b.iter(|| decode(test::black_box(&data)).unwrap().len());
+}
+
+#[bench]
+fn bench_dec_binary_large(b: &mut Bencher) {
+    let data = encode_binary(&(0..=255).cycle().take(64 * 1024).collect::<Vec<u8>>()).into_owned();
+    b.bytes = data.len() as u64;
+    b.iter(|| decode_binary(test::black_box(data.as_bytes())).len());
+}
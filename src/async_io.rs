@@ -0,0 +1,632 @@
+use crate::enc::{encode_into, EncodeOptions};
+use crate::stream::BUFFER_SIZE;
+use crate::{Decoder, EncodeSet, HexCase};
+use bytes::Bytes;
+use futures_core::Stream;
+use std::convert::Infallible;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+/// Async version of [`EncodingWriter`](crate::EncodingWriter).
+///
+/// Implements `tokio::io::AsyncWrite` with the `tokio` feature, and `futures_io::AsyncWrite` with the `futures-io` feature.
+/// The inner writer must be `Unpin` (use `Box::pin` if it isn't).
+///
+/// The encoded output is buffered. Call `flush` or `shutdown`/`close` to write it out, because it can't be done on drop.
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # async fn example() -> std::io::Result<()> {
+/// use tokio::io::AsyncWriteExt;
+/// use urlencoding::AsyncEncodingWriter;
+///
+/// let mut writer = AsyncEncodingWriter::new(Vec::new());
+/// writer.write_all("hello wörld".as_bytes()).await?;
+/// writer.shutdown().await?;
+/// assert_eq!(writer.into_inner(), b"hello%20w%C3%B6rld");
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct AsyncEncodingWriter<W> {
+    inner: W,
+    buf: Vec<u8>,
+    opts: EncodeOptions,
+}
+
+impl<W> AsyncEncodingWriter<W> {
+    /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`](crate::encode)
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::DEFAULT)
+    }
+
+    /// Percent-encodes bytes in the given set, like [`encode_with`](crate::encode_with)
+    #[inline]
+    pub fn with_set(inner: W, set: EncodeSet) -> Self {
+        Self::with_options(inner, EncodeOptions::with_set(set))
+    }
+
+    /// Encodes as `application/x-www-form-urlencoded`, like [`encode_form`](crate::encode_form)
+    #[inline]
+    pub fn form(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::FORM)
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Percent-encodes with the given [`EncodeOptions`]
+    #[inline]
+    pub fn with_options(inner: W, opts: EncodeOptions) -> Self {
+        Self {
+            inner,
+            buf: Vec::with_capacity(BUFFER_SIZE),
+            opts,
+        }
+    }
+
+    /// The inner writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// The inner writer. Writing to it directly will mix unencoded data with the buffered encoded data.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the inner writer. Data that hasn't been flushed is lost.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn poll_flush_buf<F>(&mut self, cx: &mut Context<'_>, mut write: F) -> Poll<io::Result<()>>
+    where
+        F: FnMut(Pin<&mut W>, &mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+        W: Unpin,
+    {
+        while !self.buf.is_empty() {
+            match ready!(write(Pin::new(&mut self.inner), cx, &self.buf)) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(n) => { self.buf.drain(..n); },
+                Err(e) => return Poll::Ready(Err(e)),
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write_with<F>(&mut self, cx: &mut Context<'_>, data: &[u8], write: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut(Pin<&mut W>, &mut Context<'_>, &[u8]) -> Poll<io::Result<usize>>,
+        W: Unpin,
+    {
+        // every byte can become 3 bytes, and chunks are sized to always fit in the buffer
+        let chunk = &data[..data.len().min(BUFFER_SIZE / 3)];
+        if self.buf.len() + chunk.len() * 3 > BUFFER_SIZE {
+            ready!(self.poll_flush_buf(cx, write))?;
+        }
+        let buf = &mut self.buf;
+        let _ = encode_into(chunk, false, &self.opts, |s| {
+            buf.extend_from_slice(s.as_bytes());
+            Ok::<_, Infallible>(())
+        });
+        Poll::Ready(Ok(chunk.len()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<W: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for AsyncEncodingWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::into_inner(self).poll_write_with(cx, data, |w, cx, buf| w.poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.poll_flush_buf(cx, |w, cx, buf| w.poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.poll_flush_buf(cx, |w, cx, buf| w.poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<W: futures_io::AsyncWrite + Unpin> futures_io::AsyncWrite for AsyncEncodingWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::into_inner(self).poll_write_with(cx, data, |w, cx, buf| w.poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.poll_flush_buf(cx, |w, cx, buf| w.poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = Pin::into_inner(self);
+        ready!(this.poll_flush_buf(cx, |w, cx, buf| w.poll_write(cx, buf)))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// Async version of [`DecodingReader`](crate::DecodingReader).
+///
+/// Implements `tokio::io::AsyncRead` with the `tokio` feature, and `futures_io::AsyncRead` with the `futures-io` feature.
+/// The inner reader must be `Unpin` (use `Box::pin` if it isn't).
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # async fn example() -> std::io::Result<()> {
+/// use tokio::io::AsyncReadExt;
+/// use urlencoding::AsyncDecodingReader;
+///
+/// let mut decoded = String::new();
+/// AsyncDecodingReader::new(&b"hello%20w%C3%B6rld"[..]).read_to_string(&mut decoded).await?;
+/// assert_eq!(decoded, "hello wörld");
+/// # Ok(()) }
+/// ```
+#[derive(Debug)]
+pub struct AsyncDecodingReader<R> {
+    inner: R,
+    decoder: Decoder,
+    input: Box<[u8]>,
+    decoded: Vec<u8>,
+    /// Start of unread data in `decoded`
+    pos: usize,
+    eof: bool,
+}
+
+impl<R> AsyncDecodingReader<R> {
+    /// Decodes like [`decode_binary`](crate::decode_binary)
+    #[inline]
+    pub fn new(inner: R) -> Self {
+        Self::with_decoder(inner, Decoder::new())
+    }
+
+    /// Decodes `application/x-www-form-urlencoded` data like [`decode_form_binary`](crate::decode_form_binary), with `+` as a space
+    #[inline]
+    pub fn form(inner: R) -> Self {
+        Self::with_decoder(inner, Decoder::form())
+    }
+
+    #[inline]
+    fn with_decoder(inner: R, decoder: Decoder) -> Self {
+        Self {
+            inner,
+            decoder,
+            input: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            decoded: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// The inner reader
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The inner reader. Reading from it directly will skip data that has been buffered.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the inner reader. Data that has been buffered, but not read yet, is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Copies decoded data to `out`, reading and decoding more if needed. Returns 0 only at the end.
+    fn poll_read_with<F>(&mut self, cx: &mut Context<'_>, out: &mut [u8], mut read: F) -> Poll<io::Result<usize>>
+    where
+        F: FnMut(Pin<&mut R>, &mut Context<'_>, &mut [u8]) -> Poll<io::Result<usize>>,
+        R: Unpin,
+    {
+        if out.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        // an incomplete escape decodes to nothing until the next chunk arrives
+        while self.pos >= self.decoded.len() {
+            if self.eof {
+                return Poll::Ready(Ok(0));
+            }
+            let n = ready!(read(Pin::new(&mut self.inner), cx, &mut self.input))?;
+            self.decoded.clear();
+            self.pos = 0;
+            if n == 0 {
+                self.eof = true;
+                self.decoder.finish(&mut self.decoded);
+            } else {
+                self.decoder.feed(&self.input[..n], &mut self.decoded);
+            }
+        }
+        let n = out.len().min(self.decoded.len() - self.pos);
+        out[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for AsyncDecodingReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut tokio::io::ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let n = ready!(Pin::into_inner(self).poll_read_with(cx, out.initialize_unfilled(), |r, cx, buf| {
+            let mut buf = tokio::io::ReadBuf::new(buf);
+            ready!(r.poll_read(cx, &mut buf))?;
+            Poll::Ready(Ok(buf.filled().len()))
+        }))?;
+        out.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<R: futures_io::AsyncRead + Unpin> futures_io::AsyncRead for AsyncDecodingReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, out: &mut [u8]) -> Poll<io::Result<usize>> {
+        Pin::into_inner(self).poll_read_with(cx, out, |r, cx, buf| r.poll_read(cx, buf))
+    }
+}
+
+/// Percent-encodes every chunk of the inner [`Stream`], like [`encode_binary`](crate::encode_binary).
+///
+/// The inner stream can yield anything that is `AsRef<[u8]>`, such as `Bytes` or `Vec<u8>`.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct EncodingStream<S> {
+    inner: S,
+    opts: EncodeOptions,
+}
+
+impl<S> EncodingStream<S> {
+    /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`](crate::encode)
+    #[inline]
+    pub fn new(inner: S) -> Self {
+        Self { inner, opts: EncodeOptions::DEFAULT }
+    }
+
+    /// Percent-encodes bytes in the given set, like [`encode_with`](crate::encode_with)
+    #[inline]
+    pub fn with_set(inner: S, set: EncodeSet) -> Self {
+        Self { inner, opts: EncodeOptions::with_set(set) }
+    }
+
+    /// Encodes as `application/x-www-form-urlencoded`, like [`encode_form`](crate::encode_form)
+    #[inline]
+    pub fn form(inner: S) -> Self {
+        Self { inner, opts: EncodeOptions::FORM }
+    }
+
+    /// Percent-encodes with the given [`EncodeOptions`]
+    #[inline]
+    pub fn with_options(inner: S, opts: EncodeOptions) -> Self {
+        Self { inner, opts }
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Returns the inner stream
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Stream for EncodingStream<S> where S: Stream + Unpin, S::Item: AsRef<[u8]> {
+    type Item = Bytes;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        let this = Pin::into_inner(self);
+        let Some(chunk) = ready!(Pin::new(&mut this.inner).poll_next(cx)) else {
+            return Poll::Ready(None);
+        };
+        let chunk = chunk.as_ref();
+        let mut out = Vec::with_capacity(chunk.len());
+        let _ = encode_into(chunk, false, &this.opts, |s| {
+            out.extend_from_slice(s.as_bytes());
+            Ok::<_, Infallible>(())
+        });
+        Poll::Ready(Some(out.into()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Decodes chunks of the inner [`Stream`], like [`decode_binary`](crate::decode_binary) of all the chunks concatenated.
+///
+/// Escapes split across chunks are decoded correctly. Chunks that decode to nothing are skipped.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct DecodingStream<S> {
+    inner: S,
+    decoder: Decoder,
+    done: bool,
+}
+
+impl<S> DecodingStream<S> {
+    /// Decodes like [`decode_binary`](crate::decode_binary)
+    #[inline]
+    pub fn new(inner: S) -> Self {
+        Self { inner, decoder: Decoder::new(), done: false }
+    }
+
+    /// Decodes `application/x-www-form-urlencoded` data like [`decode_form_binary`](crate::decode_form_binary), with `+` as a space
+    #[inline]
+    pub fn form(inner: S) -> Self {
+        Self { inner, decoder: Decoder::form(), done: false }
+    }
+
+    /// Returns the inner stream. An incomplete escape at the end of the data read so far is lost.
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S> Stream for DecodingStream<S> where S: Stream + Unpin, S::Item: AsRef<[u8]> {
+    type Item = Bytes;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        let this = Pin::into_inner(self);
+        let mut out = Vec::new();
+        while !this.done {
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(chunk) => this.decoder.feed(chunk.as_ref(), &mut out),
+                None => {
+                    this.done = true;
+                    this.decoder.finish(&mut out);
+                },
+            }
+            if !out.is_empty() {
+                return Poll::Ready(Some(out.into()));
+            }
+        }
+        Poll::Ready(None)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done { (0, Some(0)) } else { (0, None) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_binary, decode_form_binary, encode_form};
+    use crate::trickle::Trickle;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    struct NoopWake;
+
+    impl Wake for NoopWake {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls in a loop. The test readers and writers don't need to be woken up.
+    fn block_on<T>(fut: impl Future<Output = T>) -> T {
+        let mut fut = std::pin::pin!(fut);
+        let waker = Waker::from(Arc::new(NoopWake));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(res) = fut.as_mut().poll(&mut cx) {
+                return res;
+            }
+        }
+    }
+
+    impl Stream for Trickle<Vec<&'static [u8]>> {
+        type Item = &'static [u8];
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.is_pending() {
+                return Poll::Pending;
+            }
+            Poll::Ready(if self.data.is_empty() { None } else { Some(self.data.remove(0)) })
+        }
+    }
+
+    const SAMPLES: [&[u8]; 10] = [
+        b"", b"%", b"%%20", b"%2%", b"%zz%2x%20", b"a+b%2B+", b"%F0%9F%91%BE%20Exterminate%21",
+        b"this%20that%", b"this%20that%2", b"%25%s%1G",
+    ];
+
+    fn next_chunk<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        block_on(std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)))
+    }
+
+    #[test]
+    fn streams() {
+        for sample in SAMPLES {
+            for split in 0..sample.len() {
+                let (a, b) = sample.split_at(split);
+                let mut stream = DecodingStream::new(Trickle::new(vec![a, b, b""]));
+                let mut out = Vec::new();
+                while let Some(chunk) = next_chunk(&mut stream) {
+                    assert!(!chunk.is_empty());
+                    out.extend_from_slice(&chunk);
+                }
+                assert_eq!(out, &*decode_binary(sample));
+
+                let mut stream = DecodingStream::form(Trickle::new(vec![a, b]));
+                let mut out = Vec::new();
+                while let Some(chunk) = next_chunk(&mut stream) {
+                    out.extend_from_slice(&chunk);
+                }
+                assert_eq!(out, &*decode_form_binary(sample));
+            }
+        }
+
+        let mut stream = EncodingStream::new(Trickle::new(vec![&b"a b"[..], b"", "ö/".as_bytes()]));
+        assert_eq!(next_chunk(&mut stream).unwrap(), &b"a%20b"[..]);
+        assert_eq!(next_chunk(&mut stream).unwrap(), &b""[..]);
+        assert_eq!(next_chunk(&mut stream).unwrap(), &b"%C3%B6%2F"[..]);
+        assert_eq!(next_chunk(&mut stream), None);
+
+        let mut stream = EncodingStream::form(Trickle::new(vec![&b"a b+"[..]]));
+        assert_eq!(next_chunk(&mut stream).unwrap(), encode_form("a b+").as_bytes());
+
+        let opts = EncodeOptions::FORM.with_hex_case(HexCase::Lower);
+        let mut stream = EncodingStream::with_options(Trickle::new(vec![&b"a b+"[..]]), opts);
+        assert_eq!(next_chunk(&mut stream).unwrap(), &b"a+b%2b"[..]);
+    }
+
+    #[cfg(feature = "tokio")]
+    mod tokio_io {
+        use super::*;
+        use crate::{encode_binary, encode_binary_with_options};
+        use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+        impl AsyncRead for Trickle<&[u8]> {
+            fn poll_read(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+                let n = ready!(self.poll_read_some(buf.initialize_unfilled()))?;
+                buf.advance(n);
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        impl AsyncWrite for Trickle<Vec<u8>> {
+            fn poll_write(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+                self.poll_write_some(buf)
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        fn read_to_end<R: AsyncRead + Unpin>(mut reader: R, read_size: usize) -> Vec<u8> {
+            let mut out = Vec::new();
+            let mut buf = [0; 8];
+            loop {
+                let mut buf = ReadBuf::new(&mut buf[..read_size]);
+                block_on(std::future::poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf))).unwrap();
+                if buf.filled().is_empty() {
+                    return out;
+                }
+                out.extend_from_slice(buf.filled());
+            }
+        }
+
+        #[test]
+        fn reader_matches_decode_binary() {
+            for sample in SAMPLES {
+                for read_size in 1..6 {
+                    let out = read_to_end(AsyncDecodingReader::new(Trickle::new(sample)), read_size);
+                    assert_eq!(out, &*decode_binary(sample));
+
+                    let out = read_to_end(AsyncDecodingReader::form(Trickle::new(sample)), read_size);
+                    assert_eq!(out, &*decode_form_binary(sample));
+                }
+            }
+        }
+
+        #[test]
+        fn writer_matches_encode() {
+            let data: Vec<u8> = (0..20_000u32).map(|n| (n * 7 % 251) as u8).collect();
+            let mut w = AsyncEncodingWriter::new(Trickle::new(Vec::new()));
+            let mut rest = &data[..];
+            while !rest.is_empty() {
+                let n = block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_write(cx, rest))).unwrap();
+                rest = &rest[n..];
+            }
+            block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_shutdown(cx))).unwrap();
+            assert_eq!(w.into_inner().data, encode_binary(&data).as_bytes());
+
+            let opts = EncodeOptions::DEFAULT.with_hex_case(HexCase::Lower);
+            let mut w = AsyncEncodingWriter::with_options(Trickle::new(Vec::new()), opts);
+            let mut rest = &data[..];
+            while !rest.is_empty() {
+                let n = block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_write(cx, rest))).unwrap();
+                rest = &rest[n..];
+            }
+            block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_shutdown(cx))).unwrap();
+            assert_eq!(w.into_inner().data, encode_binary_with_options(&data, &opts).as_bytes());
+        }
+    }
+
+    #[cfg(feature = "futures-io")]
+    mod futures_io {
+        use super::*;
+        use ::futures_io::{AsyncRead, AsyncWrite};
+
+        impl AsyncRead for Trickle<&[u8]> {
+            fn poll_read(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+                self.poll_read_some(buf)
+            }
+        }
+
+        impl AsyncWrite for Trickle<Vec<u8>> {
+            fn poll_write(mut self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+                self.poll_write_some(buf)
+            }
+
+            fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+                Poll::Ready(Ok(()))
+            }
+        }
+
+        #[test]
+        fn reader_matches_decode_binary() {
+            for sample in SAMPLES {
+                for read_size in 1..6 {
+                    let mut reader = AsyncDecodingReader::form(Trickle::new(sample));
+                    let mut out = Vec::new();
+                    let mut buf = [0; 8];
+                    loop {
+                        let n = block_on(std::future::poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut buf[..read_size]))).unwrap();
+                        if n == 0 {
+                            break;
+                        }
+                        out.extend_from_slice(&buf[..n]);
+                    }
+                    assert_eq!(out, &*decode_form_binary(sample));
+                }
+            }
+        }
+
+        #[test]
+        fn writer_matches_encode() {
+            let mut w = AsyncEncodingWriter::form(Trickle::new(Vec::new()));
+            let mut rest = &b"a b+c/d"[..];
+            while !rest.is_empty() {
+                let n = block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_write(cx, rest))).unwrap();
+                rest = &rest[n..];
+            }
+            block_on(std::future::poll_fn(|cx| Pin::new(&mut w).poll_close(cx))).unwrap();
+            assert_eq!(w.into_inner().data, encode_form("a b+c/d").as_bytes());
+        }
+    }
+}
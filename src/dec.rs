@@ -1,14 +1,49 @@
-use std::borrow::Cow;
-use std::panic::panic_any;
-use std::string::FromUtf8Error;
+use crate::scan::literal_len;
+use crate::{BufferTooSmall, DecodeError};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::{fmt, str};
+#[cfg(feature = "std")]
+use std::io;
+
+/// More efficient codegen than the built-in OOM handler
+#[cold]
+fn oom() -> ! {
+    #[cfg(feature = "std")]
+    std::panic::panic_any("OOM");
+    #[cfg(not(feature = "std"))]
+    panic!("OOM");
+}
+
+/// Value of every hex digit, and `0xFF` for all other bytes
+static HEX_DIGITS: [u8; 256] = {
+    let mut table = [0xFF; 256];
+    let mut i = 0;
+    while i < 16 {
+        table[b"0123456789ABCDEF"[i] as usize] = i as u8;
+        table[b"0123456789abcdef"[i] as usize] = i as u8;
+        i += 1;
+    }
+    table
+};
 
 #[inline]
 pub(crate) fn from_hex_digit(digit: u8) -> Option<u8> {
-    match digit {
-        b'0'..=b'9' => Some(digit - b'0'),
-        b'A'..=b'F' => Some(digit - b'A' + 10),
-        b'a'..=b'f' => Some(digit - b'a' + 10),
-        _ => None,
+    let value = HEX_DIGITS[digit as usize];
+    (value < 16).then_some(value)
+}
+
+/// Decodes the two hex digits after `%`
+#[inline(always)]
+fn from_hex_pair(first: u8, second: u8) -> Option<u8> {
+    let (high, low) = (HEX_DIGITS[first as usize], HEX_DIGITS[second as usize]);
+    // a single branch for both digits
+    if (high | low) < 16 {
+        Some((high << 4) | low)
+    } else {
+        None
     }
 }
 
@@ -17,71 +52,721 @@ pub(crate) fn from_hex_digit(digit: u8) -> Option<u8> {
 /// If you need a `String`, call `.into_owned()` (not `.to_owned()`).
 ///
 /// Unencoded `+` is preserved literally, and _not_ changed to a space.
+///
+/// Fails only with [`DecodeError::InvalidUtf8`]. Malformed escapes are kept as-is.
 #[inline]
-pub fn decode(data: &str) -> Result<Cow<'_, str>, FromUtf8Error> {
+pub fn decode(data: &str) -> Result<Cow<'_, str>, DecodeError> {
     match decode_binary(data.as_bytes()) {
         Cow::Borrowed(_) => Ok(Cow::Borrowed(data)),
-        Cow::Owned(s) => Ok(Cow::Owned(String::from_utf8(s)?)),
+        Cow::Owned(s) => into_string(s, data.as_bytes()).map(Cow::Owned),
+    }
+}
+
+/// Decode percent-encoded string assuming UTF-8 encoding, and replace invalid UTF-8 with `�` (U+FFFD).
+///
+/// Same as `String::from_utf8_lossy(&decode_binary(data))`, but in a single pass, without an extra allocation.
+/// Returns `Cow::Borrowed` if the string doesn't contain `%`.
+///
+/// ```rust
+/// use urlencoding::decode_lossy;
+/// assert_eq!(decode_lossy("%F0%9F%91%BE%20%F0%9F%91"), "👾 �");
+/// ```
+#[must_use]
+pub fn decode_lossy(data: &str) -> Cow<'_, str> {
+    let data_bytes = data.as_bytes();
+    let offset = literal_len(data_bytes, false);
+    if offset >= data_bytes.len() {
+        return Cow::Borrowed(data);
+    }
+
+    // U+FFFD is 3 bytes, but it replaces at least one %xx escape, so the input length is enough
+    let mut decoded = Vec::new();
+    if decoded.try_reserve(data_bytes.len()).is_err() {
+        oom();
+    }
+    let (ascii, mut rest) = data_bytes.split_at(offset);
+    decoded.extend_from_slice(ascii);
+
+    let mut out = |s: &str| {
+        decoded.extend_from_slice(s.as_bytes());
+        Ok::<_, Infallible>(())
+    };
+    let mut utf8 = LossyUtf8::default();
+    while let Some((&c, tail)) = rest.split_first() {
+        if c == b'%' {
+            if let Some(&[first, second]) = tail.get(0..2) {
+                if let Some(byte) = from_hex_pair(first, second) {
+                    let _ = utf8.push(byte, &mut out);
+                    rest = &tail[2..];
+                    continue;
+                }
+            }
+        }
+        // Unescaped input is valid UTF-8, and begins at a char boundary
+        let _ = utf8.finish(&mut out);
+        let len = 1 + literal_len(tail, false);
+        let _ = out(unsafe { str::from_utf8_unchecked(&rest[..len]) });
+        rest = &rest[len..];
+    }
+    let _ = utf8.finish(&mut out);
+
+    Cow::Owned(unsafe {
+        // LossyUtf8 writes only valid UTF-8, and the rest has been copied from a str at char boundaries
+        String::from_utf8_unchecked(decoded)
+    })
+}
+
+/// Incremental UTF-8 validation that replaces invalid sequences the same way as `String::from_utf8_lossy`
+#[derive(Default, Debug, Clone, Copy)]
+pub(crate) struct LossyUtf8 {
+    pending: [u8; 4],
+    len: usize,
+    /// Whether any `�` has been written
+    replaced: bool,
+}
+
+impl LossyUtf8 {
+    const REPLACEMENT: &'static str = "\u{FFFD}";
+
+    /// Writes the byte, or keeps it until the UTF-8 sequence is complete
+    #[inline]
+    pub fn push<E>(&mut self, byte: u8, out: &mut impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        if self.len == 0 && byte.is_ascii() {
+            return out(unsafe { str::from_utf8_unchecked(core::slice::from_ref(&byte)) });
+        }
+        self.pending[self.len] = byte;
+        self.len += 1;
+        while self.len > 0 {
+            let err = match str::from_utf8(&self.pending[..self.len]) {
+                Ok(s) => {
+                    out(s)?;
+                    self.len = 0;
+                    return Ok(());
+                },
+                Err(err) => err,
+            };
+            let valid_len = err.valid_up_to();
+            if valid_len > 0 {
+                out(unsafe { str::from_utf8_unchecked(&self.pending[..valid_len]) })?;
+            }
+            let consumed = match err.error_len() {
+                Some(invalid_len) => {
+                    self.replaced = true;
+                    out(Self::REPLACEMENT)?;
+                    valid_len + invalid_len
+                },
+                None => valid_len, // incomplete, wait for more
+            };
+            self.pending.copy_within(consumed..self.len, 0);
+            self.len -= consumed;
+            if consumed == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as pushing every byte, but writes valid UTF-8 in one go
+    pub fn push_slice<E>(&mut self, mut bytes: &[u8], out: &mut impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        while let Some((&first, rest)) = bytes.split_first() {
+            if self.len == 0 {
+                let valid_len = match str::from_utf8(bytes) {
+                    Ok(s) => return out(s),
+                    Err(err) => err.valid_up_to(),
+                };
+                if valid_len > 0 {
+                    out(unsafe { str::from_utf8_unchecked(&bytes[..valid_len]) })?;
+                    bytes = &bytes[valid_len..];
+                    continue;
+                }
+            }
+            // the start or the rest of an incomplete or invalid sequence
+            self.push(first, out)?;
+            bytes = rest;
+        }
+        Ok(())
+    }
+
+    /// Replaces an incomplete sequence, if any
+    #[inline]
+    pub fn finish<E>(&mut self, out: &mut impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        if self.len > 0 {
+            self.len = 0;
+            self.replaced = true;
+            out(Self::REPLACEMENT)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wrapper type that implements `Display`. Decodes on the fly, without allocating.
+///
+/// Invalid UTF-8 is displayed as `�` (U+FFFD), like [`decode_lossy`]. Malformed escapes are kept as-is, like in [`decode`].
+///
+/// ```rust
+/// use urlencoding::Decoded;
+/// assert_eq!(format!("q={}", Decoded("hello%20w%C3%B6rld")), "q=hello wörld");
+/// assert_eq!(Decoded("%F0%9F%91").to_string(), "�");
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[repr(transparent)]
+pub struct Decoded<Str>(pub Str);
+
+impl<Str: AsRef<[u8]>> Decoded<Str> {
+    /// Long way of writing `Decoded(data)`
+    ///
+    /// Takes any string-like type or a slice of bytes, either owned or borrowed.
+    #[inline(always)]
+    pub fn new(string: Str) -> Self {
+        Self(string)
+    }
+
+    /// Decode to a string, replacing invalid UTF-8 with `�`
+    #[inline]
+    pub fn to_str(&self) -> Cow<'_, str> {
+        let data = self.0.as_ref();
+        if !data.contains(&b'%') {
+            return String::from_utf8_lossy(data);
+        }
+        let mut string = String::with_capacity(data.len());
+        self.append_to(&mut string);
+        Cow::Owned(string)
+    }
+
+    /// Decode to a string, replacing invalid UTF-8 with `�`
+    #[inline]
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        self.to_str().into_owned()
+    }
+
+    /// Decode into a writer, replacing invalid UTF-8 with `�`
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.decode_with(|s| writer.write_all(s.as_bytes())).map(drop)
+    }
+
+    /// Decode into a writer, and fail if the decoded data isn't valid UTF-8.
+    ///
+    /// The data is checked before anything is written. The error has [`io::ErrorKind::InvalidData`] kind,
+    /// and wraps [`DecodeError::InvalidUtf8`].
+    ///
+    /// ```rust
+    /// use urlencoding::{Decoded, DecodeError};
+    ///
+    /// let mut out = Vec::new();
+    /// let err = Decoded("ok%FF").try_write(&mut out).unwrap_err();
+    /// assert_eq!(err.get_ref().unwrap().downcast_ref::<DecodeError>().unwrap().offset(), 2);
+    /// assert!(out.is_empty());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn try_write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let replaced = self.decode_with(|_| Ok::<_, Infallible>(())).unwrap_or_default();
+        if replaced {
+            let data = self.0.as_ref();
+            if let Err(err) = into_string(decode_binary(data).into_owned(), data) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+        }
+        self.write(writer)
+    }
+
+    /// Decode into a string, replacing invalid UTF-8 with `�`
+    #[inline]
+    pub fn append_to(&self, string: &mut String) {
+        let _ = self.decode_with(|s| {
+            string.push_str(s);
+            Ok::<_, Infallible>(())
+        });
+    }
+
+    /// Returns whether any `�` has been written in place of invalid UTF-8
+    fn decode_with<E>(&self, mut out: impl FnMut(&str) -> Result<(), E>) -> Result<bool, E> {
+        let mut utf8 = LossyUtf8::default();
+        let mut rest = self.0.as_ref();
+        loop {
+            let (literal, tail) = rest.split_at(literal_len(rest, false));
+            utf8.push_slice(literal, &mut out)?;
+            let escaped = match *tail {
+                [_, first, second, ..] => from_hex_pair(first, second),
+                _ => None,
+            };
+            rest = match escaped {
+                Some(byte) => {
+                    utf8.push(byte, &mut out)?;
+                    &tail[3..]
+                },
+                None if tail.is_empty() => break,
+                None => {
+                    utf8.push(b'%', &mut out)?;
+                    &tail[1..]
+                },
+            };
+        }
+        utf8.finish(&mut out)?;
+        Ok(utf8.replaced)
+    }
+}
+
+impl<Str: AsRef<[u8]>> fmt::Display for Decoded<Str> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.decode_with(|s| f.write_str(s)).map(drop)
+    }
+}
+
+/// Decode `application/x-www-form-urlencoded` string assuming UTF-8 encoding.
+///
+/// Same as [`decode`], except `+` is changed to a space, the way HTML forms encode it.
+///
+/// ```rust
+/// use urlencoding::decode_form;
+/// assert_eq!(decode_form("1+%2B+1").unwrap(), "1 + 1");
+/// ```
+#[inline]
+pub fn decode_form(data: &str) -> Result<Cow<'_, str>, DecodeError> {
+    match decode_form_binary(data.as_bytes()) {
+        Cow::Borrowed(_) => Ok(Cow::Borrowed(data)),
+        Cow::Owned(s) => into_string(s, data.as_bytes()).map(Cow::Owned),
+    }
+}
+
+/// Validates UTF-8 of the `decoded` bytes, and reports errors at their position in the `encoded` input
+fn into_string(decoded: Vec<u8>, encoded: &[u8]) -> Result<String, DecodeError> {
+    String::from_utf8(decoded).map_err(|e| {
+        let offset = encoded_offset(encoded, e.utf8_error().valid_up_to());
+        DecodeError::InvalidUtf8 { offset, decoded: e.into_bytes() }
+    })
+}
+
+/// Same as `String::from_utf8_lossy(&bytes).into_owned()`, but keeps the valid prefix in place,
+/// and converts only the rest
+pub(crate) fn into_string_lossy(bytes: Vec<u8>) -> String {
+    let (valid_len, mut bytes) = match String::from_utf8(bytes) {
+        Ok(s) => return s,
+        Err(e) => (e.utf8_error().valid_up_to(), e.into_bytes()),
+    };
+    let rest = bytes.split_off(valid_len);
+    let mut out = |s: &str| {
+        bytes.extend_from_slice(s.as_bytes());
+        Ok::<_, Infallible>(())
+    };
+    let mut utf8 = LossyUtf8::default();
+    let _ = utf8.push_slice(&rest, &mut out);
+    let _ = utf8.finish(&mut out);
+    unsafe {
+        // the prefix has been validated, and LossyUtf8 writes only valid UTF-8
+        String::from_utf8_unchecked(bytes)
     }
 }
 
 /// Decode percent-encoded string as binary data, in any encoding.
 ///
 /// Unencoded `+` is preserved literally, and _not_ changed to a space.
+#[inline]
 #[must_use]
 pub fn decode_binary(data: &[u8]) -> Cow<'_, [u8]> {
-    let offset = data.iter().take_while(|&&c| c != b'%').count();
-    if offset >= data.len() {
+    decode_binary_internal(data, false)
+}
+
+/// Decode `application/x-www-form-urlencoded` data as binary data, in any encoding.
+///
+/// Same as [`decode_binary`], except `+` is changed to a space.
+#[inline]
+#[must_use]
+pub fn decode_form_binary(data: &[u8]) -> Cow<'_, [u8]> {
+    decode_binary_internal(data, true)
+}
+
+/// Decode percent-encoded data in place, without allocating. Returns the decoded part of the buffer.
+///
+/// The decoded data is never longer than the input. Bytes after the decoded part are left in an unspecified state.
+///
+/// ```rust
+/// use urlencoding::decode_in_place;
+///
+/// let mut path = *b"/a%20b";
+/// assert_eq!(decode_in_place(&mut path), b"/a b");
+/// ```
+pub fn decode_in_place(data: &mut [u8]) -> &mut [u8] {
+    let (len, _) = decode_in_place_internal(data, false);
+    &mut data[..len]
+}
+
+/// Decode percent-encoded data, reusing the `Vec`'s buffer.
+///
+/// Same as [`decode_binary`], but never allocates.
+#[must_use]
+pub fn decode_vec_in_place(mut data: Vec<u8>) -> Vec<u8> {
+    let (len, _) = decode_in_place_internal(&mut data, false);
+    data.truncate(len);
+    data
+}
+
+/// Decode percent-encoded string assuming UTF-8 encoding, reusing the `String`'s buffer.
+///
+/// Same as [`decode`], but never allocates.
+///
+/// ```rust
+/// use urlencoding::decode_string_in_place;
+///
+/// let segment = String::from("w%C3%B6rld");
+/// assert_eq!(decode_string_in_place(segment)?, "wörld");
+/// # Ok::<_, urlencoding::DecodeError>(())
+/// ```
+pub fn decode_string_in_place(data: String) -> Result<String, DecodeError> {
+    let mut data = data.into_bytes();
+    let (len, invalid_utf8_at) = decode_in_place_internal(&mut data, true);
+    data.truncate(len);
+    if let Some(offset) = invalid_utf8_at {
+        return Err(DecodeError::InvalidUtf8 { offset, decoded: data });
+    }
+    Ok(unsafe {
+        // the literal parts were from a String, and the decoded escapes have been validated
+        String::from_utf8_unchecked(data)
+    })
+}
+
+/// Returns decoded length, and offset in the input of the first invalid UTF-8 sequence.
+///
+/// If `check_utf8` is set, the input must be valid UTF-8.
+fn decode_in_place_internal(data: &mut [u8], check_utf8: bool) -> (usize, Option<usize>) {
+    let start = literal_len(data, false);
+    if start == data.len() {
+        return (data.len(), None);
+    }
+    let mut invalid_utf8_at = None;
+    // the output is never longer than the input, so writes never overwrite unread input
+    let mut read = start;
+    let mut write = start;
+    while read < data.len() {
+        // decode a run of %xx escapes
+        let run_start = (read, write);
+        while let [b'%', first, second, ..] = data[read..] {
+            let Some(byte) = from_hex_pair(first, second) else {
+                break;
+            };
+            data[write] = byte;
+            read += 3;
+            write += 1;
+        }
+        // unescaped parts are complete chars, so each run of escapes must be valid UTF-8 by itself
+        if check_utf8 && invalid_utf8_at.is_none() {
+            if let Err(e) = str::from_utf8(&data[run_start.1..write]) {
+                invalid_utf8_at = Some(run_start.0 + 3 * e.valid_up_to());
+            }
+        }
+
+        // a malformed escape is kept as-is
+        if data.get(read) == Some(&b'%') {
+            data[write] = b'%';
+            read += 1;
+            write += 1;
+        }
+        let len = literal_len(&data[read..], false);
+        data.copy_within(read..read + len, write);
+        read += len;
+        write += len;
+    }
+    (write, invalid_utf8_at)
+}
+
+pub(crate) fn decode_binary_internal(data: &[u8], plus_as_space: bool) -> Cow<'_, [u8]> {
+    if literal_len(data, plus_as_space) >= data.len() {
         return Cow::Borrowed(data);
     }
 
     let mut decoded = Vec::new();
     if decoded.try_reserve(data.len()).is_err() {
-        panic_any("OOM"); // more efficient codegen than built-in OOM handler
+        oom();
     }
     let mut out = NeverRealloc(&mut decoded);
+    decode_into(data, &mut out, plus_as_space, true);
+    Cow::Owned(decoded)
+}
 
-    let (ascii, mut data) = data.split_at(offset);
-    out.extend_from_slice(ascii);
+/// Decode percent-encoded data into the given buffer, without allocating. Returns length of the decoded data.
+///
+/// The decoded data is never longer than the input, so a buffer of `data.len()` bytes is always large enough.
+/// Use [`decoded_len`] for the exact size. If the buffer is too small, its contents are unspecified.
+///
+/// ```rust
+/// use urlencoding::decode_to_slice;
+///
+/// let mut buf = [0; 16];
+/// let len = decode_to_slice(b"%F0%9F%91%BE!", &mut buf)?;
+/// assert_eq!(&buf[..len], "👾!".as_bytes());
+/// # Ok::<_, urlencoding::BufferTooSmall>(())
+/// ```
+pub fn decode_to_slice(data: &[u8], out: &mut [u8]) -> Result<usize, BufferTooSmall> {
+    let mut sink = SliceSink { buf: out, len: 0 };
+    decode_into(data, &mut sink, false, true);
+    if sink.len > sink.buf.len() {
+        return Err(BufferTooSmall { required: sink.len });
+    }
+    Ok(sink.len)
+}
 
+/// Length of the data that [`decode_binary`] would return, without decoding it
+#[must_use]
+pub fn decoded_len(data: &[u8]) -> usize {
+    let mut sink = SliceSink { buf: &mut [], len: 0 };
+    decode_into(data, &mut sink, false, true);
+    sink.len
+}
+
+/// Output of [`decode_into`]
+trait DecodeSink {
+    fn push(&mut self, byte: u8);
+    fn extend_from_slice(&mut self, bytes: &[u8]);
+
+    /// Same as `extend_from_slice(&data[..len])`
+    #[inline]
+    fn extend_from_prefix(&mut self, data: &[u8], len: usize) {
+        self.extend_from_slice(&data[..len]);
+    }
+}
+
+impl DecodeSink for NeverRealloc<'_, u8> {
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        NeverRealloc::push(self, byte);
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        NeverRealloc::extend_from_slice(self, bytes);
+    }
+
+    #[inline]
+    fn extend_from_prefix(&mut self, data: &[u8], len: usize) {
+        let vec = &mut *self.0;
+        // Copying a fixed 16 bytes is much faster than a memcpy call for short literals.
+        // The bytes after `len` are in the spare capacity, and will be overwritten.
+        if len <= 16 && data.len() >= 16 && vec.capacity() - vec.len() >= 16 {
+            unsafe {
+                vec.as_mut_ptr().add(vec.len()).copy_from_nonoverlapping(data.as_ptr(), 16);
+                vec.set_len(vec.len() + len);
+            }
+        } else {
+            NeverRealloc::extend_from_slice(self, &data[..len]);
+        }
+    }
+}
+
+/// Writes as much as fits, but counts all bytes
+struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl DecodeSink for SliceSink<'_> {
+    #[inline]
+    fn push(&mut self, byte: u8) {
+        if let Some(dst) = self.buf.get_mut(self.len) {
+            *dst = byte;
+        }
+        self.len += 1;
+    }
+
+    #[inline]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if let Some(dst) = self.buf.get_mut(self.len..self.len + bytes.len()) {
+            dst.copy_from_slice(bytes);
+        }
+        self.len += bytes.len();
+    }
+}
+
+/// The decoding loop shared by all non-strict decoders. Returns number of bytes consumed.
+///
+/// If it's not `at_eof`, an incomplete escape at the end of the `data` is left unconsumed.
+/// `out` must have capacity for `data.len()` more bytes.
+fn decode_into(mut data: &[u8], out: &mut impl DecodeSink, plus_as_space: bool, at_eof: bool) -> usize {
+    let data_len = data.len();
     loop {
-        let mut parts = data.splitn(2, |&c| c == b'%');
         // first the decoded non-% part
-        let non_escaped_part = parts.next().unwrap();
-        let rest = parts.next();
-        if rest.is_none() && out.0.is_empty() {
-            // if empty there were no '%' in the string
-            return data.into();
-        }
-        out.extend_from_slice(non_escaped_part);
-
-        // then decode one %xx
-        match rest {
-            Some(rest) => {
-                if let Some(&[first, second]) = rest.get(0..2) {
-                    if let Some(first_val) = from_hex_digit(first) {
-                        if let Some(second_val) = from_hex_digit(second) {
-                            out.push((first_val << 4) | second_val);
-                            data = &rest[2..];
-                        } else {
-                            out.extend_from_slice(&[b'%', first]);
-                            data = &rest[1..];
-                        }
+        let len = literal_len(data, plus_as_space);
+        out.extend_from_prefix(data, len);
+        data = &data[len..];
+
+        // then decode a run of %xx or +, without searching for the next % between them
+        loop {
+            match *data {
+                [b'%', first, second, ..] => {
+                    if let Some(byte) = from_hex_pair(first, second) {
+                        out.push(byte);
+                        data = &data[3..];
                     } else {
                         out.push(b'%');
-                        data = rest;
+                        data = &data[1..];
+                        break;
                     }
-                } else {
-                    // too short
+                },
+                [b'+', ..] if plus_as_space => {
+                    out.push(b' ');
+                    data = &data[1..];
+                },
+                [] => return data_len,
+                // too short, but it could be completed by the next bytes
+                [b'%'] if !at_eof => return data_len - 1,
+                [b'%', first] if !at_eof && from_hex_digit(first).is_some() => return data_len - 2,
+                [b'%', ..] => {
                     out.push(b'%');
-                    out.extend_from_slice(rest);
+                    data = &data[1..];
                     break;
-                }
+                },
+                _ => break,
+            }
+        }
+    }
+}
+
+/// Push-based decoder for data that arrives in chunks, without any I/O.
+///
+/// Gives exactly the same result as [`decode_binary`] (or [`decode_form_binary`]) of all the chunks concatenated.
+/// Escapes split across chunks are kept until the next [`feed`](Self::feed).
+///
+/// ```rust
+/// use urlencoding::Decoder;
+///
+/// let mut decoder = Decoder::new();
+/// let mut out = Vec::new();
+/// decoder.feed(b"hello%2", &mut out);
+/// decoder.feed(b"0world%", &mut out);
+/// decoder.finish(&mut out);
+/// assert_eq!(out, b"hello world%");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    /// Incomplete escape: `%` and maybe one hex digit
+    pending: [u8; 2],
+    pending_len: usize,
+    plus_as_space: bool,
+}
+
+impl Decoder {
+    /// Decodes like [`decode_binary`]
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes `application/x-www-form-urlencoded` data like [`decode_form_binary`], with `+` as a space
+    #[inline]
+    #[must_use]
+    pub fn form() -> Self {
+        Self { plus_as_space: true, ..Self::default() }
+    }
+
+    /// Decodes the next chunk of input, and appends the result to `out`
+    pub fn feed(&mut self, mut input: &[u8], out: &mut Vec<u8>) {
+        out.reserve(input.len() + self.pending_len);
+        let mut out = NeverRealloc(out);
+
+        if self.pending_len > 0 {
+            // two more bytes are enough to complete the escape from the previous chunk
+            let mut joined = [0; 4];
+            let taken = input.len().min(2);
+            joined[..self.pending_len].copy_from_slice(&self.pending[..self.pending_len]);
+            joined[self.pending_len..self.pending_len + taken].copy_from_slice(&input[..taken]);
+            let joined = &joined[..self.pending_len + taken];
+
+            let consumed = decode_into(joined, &mut out, self.plus_as_space, false);
+            if consumed < self.pending_len {
+                // the input was too short to complete it
+                self.set_pending(&joined[consumed..]);
+                return;
+            }
+            input = &input[consumed - self.pending_len..];
+        }
+
+        let consumed = decode_into(input, &mut out, self.plus_as_space, false);
+        self.set_pending(&input[consumed..]);
+    }
+
+    /// Writes out an incomplete escape left at the end of the input, if any, and resets the decoder.
+    pub fn finish(&mut self, out: &mut Vec<u8>) {
+        out.reserve(self.pending_len);
+        let pending = self.pending;
+        decode_into(&pending[..self.pending_len], &mut NeverRealloc(out), self.plus_as_space, true);
+        self.pending_len = 0;
+    }
+
+    #[inline]
+    fn set_pending(&mut self, incomplete: &[u8]) {
+        self.pending[..incomplete.len()].copy_from_slice(incomplete);
+        self.pending_len = incomplete.len();
+    }
+}
+
+/// Decode percent-encoded string assuming UTF-8 encoding, rejecting malformed input.
+///
+/// Unlike [`decode`], which passes incomplete escapes like `%`, `%2` or `%zz` through as-is,
+/// this reports them as errors.
+///
+/// ```rust
+/// use urlencoding::{decode_strict, DecodeError};
+/// assert_eq!(decode_strict("a%20b").unwrap(), "a b");
+/// assert_eq!(decode_strict("a%2"), Err(DecodeError::TruncatedEscape { offset: 1 }));
+/// assert_eq!(decode_strict("a%zz"), Err(DecodeError::InvalidHexDigit { offset: 1 }));
+/// assert_eq!(decode_strict("ok%FF").unwrap_err().offset(), 2);
+/// ```
+pub fn decode_strict(data: &str) -> Result<Cow<'_, str>, DecodeError> {
+    match decode_binary_strict(data.as_bytes())? {
+        Cow::Borrowed(_) => Ok(Cow::Borrowed(data)),
+        Cow::Owned(s) => into_string(s, data.as_bytes()).map(Cow::Owned),
+    }
+}
+
+/// Decode percent-encoded string as binary data, in any encoding, rejecting malformed escapes.
+///
+/// Unlike [`decode_binary`], which passes incomplete escapes like `%`, `%2` or `%zz` through as-is,
+/// this reports them as errors.
+pub fn decode_binary_strict(data: &[u8]) -> Result<Cow<'_, [u8]>, DecodeError> {
+    let offset = literal_len(data, false);
+    if offset >= data.len() {
+        return Ok(Cow::Borrowed(data));
+    }
+
+    let mut decoded = Vec::new();
+    if decoded.try_reserve(data.len()).is_err() {
+        oom();
+    }
+    let mut out = NeverRealloc(&mut decoded);
+    out.extend_from_slice(&data[..offset]);
+
+    let mut pos = offset;
+    while pos < data.len() {
+        let non_escaped_len = literal_len(&data[pos..], false);
+        out.extend_from_slice(&data[pos..pos + non_escaped_len]);
+        pos += non_escaped_len;
+
+        match data[pos..] {
+            [] => break,
+            [_, first, second, ..] => match from_hex_pair(first, second) {
+                Some(byte) => out.push(byte),
+                None => return Err(DecodeError::InvalidHexDigit { offset: pos }),
             },
-            None => break,
+            [_, first] if from_hex_digit(first).is_none() => return Err(DecodeError::InvalidHexDigit { offset: pos }),
+            _ => return Err(DecodeError::TruncatedEscape { offset: pos }),
         }
+        pos += 3;
     }
-    Cow::Owned(decoded)
+    Ok(Cow::Owned(decoded))
+}
+
+/// Maps position in the decoded output back to the position in the encoded input
+pub(crate) fn encoded_offset(encoded: &[u8], decoded_offset: usize) -> usize {
+    let mut pos = 0;
+    for _ in 0..decoded_offset {
+        pos += match encoded.get(pos..pos + 3) {
+            Some(&[b'%', first, second]) if from_hex_pair(first, second).is_some() => 3,
+            _ => 1,
+        };
+    }
+    pos
 }
 
 struct NeverRealloc<'a, T>(pub &'a mut Vec<T>);
@@ -104,6 +789,160 @@ impl<T> NeverRealloc<'_, T> {
     }
 }
 
+#[test]
+fn dec_form() {
+    assert!(matches!(decode_form("hello%2Bworld"), Ok(Cow::Owned(s)) if s == "hello+world"));
+    assert!(matches!(decode_form("hello+world"), Ok(Cow::Owned(s)) if s == "hello world"));
+    assert!(matches!(decode_form("hello_world"), Ok(Cow::Borrowed("hello_world"))));
+    assert!(matches!(decode("hello+world"), Ok(Cow::Borrowed("hello+world"))));
+    assert_eq!(*decode_form_binary(b"++%20+%2+%zz+%"), b"    %2 %zz %"[..]);
+    assert_eq!(*decode_form_binary(b"+"), b" "[..]);
+}
+
+#[test]
+fn dec_strict() {
+    assert!(matches!(decode_strict("hello"), Ok(Cow::Borrowed("hello"))));
+    assert!(matches!(decode_strict("%F0%9F%91%BE%20Exterminate%21"), Ok(Cow::Owned(s)) if s == "👾 Exterminate!"));
+    assert_eq!(decode_binary_strict(b"%00%ff%Fa+").unwrap(), &b"\0\xFF\xFA+"[..]);
+    assert_eq!(decode_binary_strict(b"%"), Err(DecodeError::TruncatedEscape { offset: 0 }));
+    assert_eq!(decode_binary_strict(b"ab%2"), Err(DecodeError::TruncatedEscape { offset: 2 }));
+    assert_eq!(decode_binary_strict(b"ab%x"), Err(DecodeError::InvalidHexDigit { offset: 2 }));
+    assert_eq!(decode_binary_strict(b"%20%2x"), Err(DecodeError::InvalidHexDigit { offset: 3 }));
+    assert_eq!(decode_binary_strict(b"%20%%20"), Err(DecodeError::InvalidHexDigit { offset: 3 }));
+    assert_eq!(decode_binary_strict(b"%20%x2"), Err(DecodeError::InvalidHexDigit { offset: 3 }));
+
+    // the error points to the escape that starts the invalid sequence
+    assert!(matches!(decode_strict("ą%C4"), Err(DecodeError::InvalidUtf8 { offset: 2, .. })));
+    assert!(matches!(decode_strict("%C4%85x%C4%C4%85"), Err(DecodeError::InvalidUtf8 { offset: 7, .. })));
+    assert_eq!(decode_strict("%20%80").unwrap_err().offset(), 3);
+    assert_eq!(decode_strict("%").unwrap_err().to_string(), "incomplete percent-escape at 0");
+}
+
+#[test]
+fn dec_errors() {
+    let err = decode("%20ą%C4%FFx").unwrap_err();
+    assert_eq!(err.offset(), 5);
+    assert_eq!(err.as_bytes(), b" \xC4\x85\xC4\xFFx");
+    assert_eq!(err.utf8_error().unwrap().valid_up_to(), 3);
+    assert_eq!(err.clone().into_from_utf8_error().unwrap().into_bytes(), err.as_bytes());
+    assert_eq!(err.into_bytes(), b" \xC4\x85\xC4\xFFx");
+
+    // malformed escapes are passed through by the lenient decoder
+    assert_eq!(decode("%%2%zz%FF").unwrap_err().offset(), 6);
+    assert_eq!(decode_form("+%+%FF").unwrap_err().offset(), 3);
+
+    let err = decode_strict("%").unwrap_err();
+    assert!(err.as_bytes().is_empty());
+    assert_eq!(err.utf8_error(), None);
+    assert_eq!(err.into_from_utf8_error(), None);
+}
+
+/// 5000 pseudo-random concatenations of up to 6 parts, the same on every run
+#[cfg(test)]
+fn joined_parts<T: AsRef<[u8]>>(parts: &[T]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let mut seed = 1u32;
+    (0..5000).map(move |_| {
+        let mut data = Vec::new();
+        for _ in 0..(seed % 7) {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            data.extend_from_slice(parts[(seed >> 16) as usize % parts.len()].as_ref());
+        }
+        data
+    })
+}
+
+#[test]
+fn dec_lossy() {
+    assert!(matches!(decode_lossy("hello+world"), Cow::Borrowed("hello+world")));
+    assert!(matches!(decode_lossy("ą%20"), Cow::Owned(s) if s == "ą "));
+
+    let samples = [
+        "%", "%2", "%%", "%zz", "a%FFb", "%E2%89", "%E2%89%A1", "%E2%89x", "%E2ą", "ą%85", "%C4%85%85",
+        "%F0%9F%91%BE%20Exterminate%21", "%F0%9F%91", "%F0%9F%91%", "%F0%9F%91%zz", "%ED%A0%80", "%F4%90%80%80",
+        "%C0%AF", "%E0%80%AF", "%FE%FF", "%F0%9F%E2%89%A1%91%BE", "≡%E2%89%A1‽%E2%80%BD%E2%80", "%C3",
+    ];
+    for s in samples {
+        assert_eq!(decode_lossy(s), String::from_utf8_lossy(&decode_binary(s.as_bytes())), "{s}");
+        assert_eq!(into_string_lossy(decode_binary(s.as_bytes()).into_owned()), decode_lossy(s), "{s}");
+    }
+
+    // pseudo-random fragments of escapes
+    let parts = ["%E2", "%89", "%A1", "%F0", "%9F", "%80", "%C3", "%FF", "%", "ą", "a", "%2", "%ED", "%A0"];
+    for s in joined_parts(&parts).map(|s| String::from_utf8(s).unwrap()) {
+        assert_eq!(decode_lossy(&s), String::from_utf8_lossy(&decode_binary(s.as_bytes())), "{s}");
+        assert_eq!(into_string_lossy(decode_binary(s.as_bytes()).into_owned()), decode_lossy(&s), "{s}");
+        assert!(decode_lossy(&s).len() <= s.len());
+    }
+}
+
+#[test]
+fn decoded_display() {
+    assert!(matches!(Decoded("a+b").to_str(), Cow::Borrowed("a+b")));
+    assert_eq!(Decoded(&b"\xFF%20"[..]).to_string(), "\u{FFFD} ");
+
+    let parts: [&[u8]; 14] = [b"%E2", b"%89", b"%A1", b"%F0", b"%9F", b"\xE2", b"\x89", b"\xA1", b"%", "ą".as_bytes(), b"a", b"%2", b"%ED", b"%A0"];
+    for data in joined_parts(&parts) {
+        let expected = String::from_utf8_lossy(&decode_binary(&data)).into_owned();
+        assert_eq!(Decoded(&data).to_str(), expected);
+        assert_eq!(format!("{}", Decoded(&data)), expected);
+
+        #[cfg(feature = "std")]
+        {
+            let mut written = Vec::new();
+            Decoded(&data).write(&mut written).unwrap();
+            assert_eq!(written, expected.as_bytes());
+
+            let mut written = Vec::new();
+            match Decoded(&data).try_write(&mut written) {
+                Ok(()) => assert_eq!(written, &*decode_binary(&data)),
+                Err(err) => {
+                    assert!(written.is_empty());
+                    assert!(str::from_utf8(&decode_binary(&data)).is_err());
+                    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+                },
+            }
+        }
+    }
+}
+
+#[test]
+fn dec_in_place() {
+    let parts = ["%E2", "%89", "%A1", "%F0", "%9F", "%80", "%C3", "%FF", "%", "ą", "a", "%2", "%zz", "%25", "%20", "+"];
+    for s in joined_parts(&parts).map(|s| String::from_utf8(s).unwrap()) {
+        let mut bytes = s.clone().into_bytes();
+        assert_eq!(decode_in_place(&mut bytes), &*decode_binary(s.as_bytes()), "{s}");
+        assert_eq!(decode_vec_in_place(s.clone().into_bytes()), &*decode_binary(s.as_bytes()), "{s}");
+        assert_eq!(decode_string_in_place(s.clone()), decode(&s).map(Cow::into_owned), "{s}");
+    }
+}
+
+#[test]
+fn decoder_matches_decode_binary() {
+    let samples: [&[u8]; 14] = [
+        b"", b"%", b"%%", b"%2", b"%2%", b"%%20", b"%zz%2x%20", b"a+b%2B+", b"%F0%9F%91%BE%20Exterminate%21",
+        b"this%20that%", b"this%20that%2", b"%25%s%1G", b"%+%2+%%+", b"+%%%2%%%",
+    ];
+    for sample in samples {
+        for split in 1..5 {
+            for (mut decoder, expected) in [(Decoder::new(), decode_binary(sample)), (Decoder::form(), decode_form_binary(sample))] {
+                let mut out = Vec::new();
+                for chunk in sample.chunks(split) {
+                    decoder.feed(chunk, &mut out);
+                    decoder.feed(b"", &mut out);
+                }
+                decoder.finish(&mut out);
+                assert_eq!(out, &*expected, "{split} {}", String::from_utf8_lossy(sample));
+            }
+        }
+    }
+    let mut out = Vec::new();
+    let mut decoder = Decoder::new();
+    decoder.feed(b"%4", &mut out);
+    decoder.finish(&mut out);
+    decoder.feed(b"1", &mut out);
+    assert_eq!(out, b"%41");
+}
+
 #[test]
 fn dec_borrows() {
     assert!(matches!(decode("hello"), Ok(Cow::Borrowed("hello"))));
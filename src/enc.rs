@@ -1,8 +1,82 @@
-use std::borrow::Cow;
-use std::{fmt, io, str};
+use crate::scan::unescaped_prefix_len;
+use crate::{BufferTooSmall, EncodeSet, PercentEncodedStr, PercentEncodedString};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::{fmt, str};
+#[cfg(feature = "std")]
+use std::io;
 
-const fn ascii_checker(c: &&u8) -> bool {
-    matches!(c, b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' |  b'-' | b'.' | b'_' | b'~')
+/// Letter case of the hex digits in percent-escapes
+///
+/// ```rust
+/// use urlencoding::{Encoded, HexCase};
+/// assert_eq!(Encoded("≡").to_string(), "%E2%89%A1");
+/// assert_eq!(Encoded("≡").hex_case(HexCase::Lower).to_string(), "%e2%89%a1");
+/// ```
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum HexCase {
+    /// `%2F`, recommended by RFC 3986, and the default
+    #[default]
+    Upper,
+    /// `%2f`
+    Lower,
+}
+
+impl HexCase {
+    #[inline]
+    const fn digits(self) -> &'static [u8; 16] {
+        match self {
+            Self::Upper => b"0123456789ABCDEF",
+            Self::Lower => b"0123456789abcdef",
+        }
+    }
+}
+
+/// How to percent-encode: which bytes to escape, whether spaces become `+`, and the letter case of the hex digits.
+///
+/// Taken by the `_with_options` variants of the encoding functions, and by the encoding writers.
+///
+/// ```rust
+/// use urlencoding::{encode_with_options, EncodeOptions, EncodeSet, HexCase};
+///
+/// const LOWER_PATH: EncodeOptions = EncodeOptions::with_set(EncodeSet::PATH).with_hex_case(HexCase::Lower);
+/// assert_eq!(encode_with_options("/ä b", &LOWER_PATH), "/%c3%a4%20b");
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EncodeOptions {
+    pub(crate) set: EncodeSet,
+    /// Write `+` instead of `%20`
+    pub(crate) space_as_plus: bool,
+    pub(crate) hex_case: HexCase,
+}
+
+impl EncodeOptions {
+    /// Escapes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`]
+    pub const DEFAULT: Self = Self { set: EncodeSet::DEFAULT, space_as_plus: false, hex_case: HexCase::Upper };
+    /// Encodes as `application/x-www-form-urlencoded`, like [`encode_form`]
+    pub const FORM: Self = Self { set: EncodeSet::WHATWG_FORM, space_as_plus: true, hex_case: HexCase::Upper };
+
+    /// Escapes bytes in the given set, like [`encode_with`]
+    #[inline]
+    #[must_use]
+    pub const fn with_set(set: EncodeSet) -> Self {
+        Self { set, ..Self::DEFAULT }
+    }
+
+    /// Same options, but with the given `hex_case`
+    #[inline]
+    #[must_use]
+    pub const fn with_hex_case(self, hex_case: HexCase) -> Self {
+        Self { hex_case, ..self }
+    }
+}
+
+impl Default for EncodeOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
 }
 
 /// Wrapper type that implements `Display`. Encodes on the fly, without allocating.
@@ -25,22 +99,76 @@ impl<Str: AsRef<[u8]>> Encoded<Str> {
         Self(string)
     }
 
+    /// Percent-encode using a custom set of bytes to escape, instead of the default one.
+    ///
+    /// ```rust
+    /// use urlencoding::{Encoded, EncodeSet};
+    /// let path = Encoded("a b/c").with_set(EncodeSet::DEFAULT.remove(b'/'));
+    /// assert_eq!("a%20b/c", path.to_string());
+    /// ```
     #[inline(always)]
-    pub fn to_str(&self) -> Cow<'_, str> {
-        encode_binary_internal(self.0.as_ref(), ascii_checker)
+    #[must_use]
+    pub fn with_set(self, set: EncodeSet) -> EncodedWith<Str> {
+        EncodedWith { data: self.0, opts: EncodeOptions::with_set(set) }
+    }
+
+    /// Encodes with the given [`EncodeOptions`]
+    #[inline]
+    #[must_use]
+    pub fn with_options(self, opts: EncodeOptions) -> EncodedWith<Str> {
+        EncodedWith { data: self.0, opts }
+    }
+
+    /// Percent-encode as `application/x-www-form-urlencoded`, like HTML forms do.
+    /// Spaces are encoded as `+`. See [`encode_form`].
+    ///
+    /// ```rust
+    /// use urlencoding::Encoded;
+    /// assert_eq!("a+b%2Bc", Encoded("a b+c").form().to_string());
+    /// ```
+    #[inline(always)]
+    #[must_use]
+    pub fn form(self) -> EncodedWith<Str> {
+        EncodedWith { data: self.0, opts: EncodeOptions::FORM }
+    }
+
+    /// Percent-encode with lowercase hex digits, like `%2f`, instead of the default uppercase.
+    ///
+    /// ```rust
+    /// use urlencoding::{Encoded, HexCase};
+    /// assert_eq!("a%2fb", Encoded("a/b").hex_case(HexCase::Lower).to_string());
+    /// ```
+    #[inline(always)]
+    #[must_use]
+    pub fn hex_case(self, hex_case: HexCase) -> EncodedWith<Str> {
+        EncodedWith { data: self.0, opts: EncodeOptions::DEFAULT.with_hex_case(hex_case) }
+    }
+
+    /// Perform urlencoding. The type keeps track that the string is already percent-encoded.
+    ///
+    /// ```rust
+    /// use urlencoding::Encoded;
+    /// let name = Encoded("Zoë").to_str();
+    /// assert_eq!(name.as_str(), "Zo%C3%AB");
+    /// assert_eq!(name.decode().unwrap(), "Zoë");
+    /// ```
+    #[inline(always)]
+    pub fn to_str(&self) -> Cow<'_, PercentEncodedStr> {
+        encode_checked(self.0.as_ref(), &EncodeOptions::DEFAULT)
     }
 
     /// Perform urlencoding to a string
     #[inline]
     #[allow(clippy::inherent_to_string_shadow_display)]
     pub fn to_string(&self) -> String {
-        self.to_str().into_owned()
+        self.to_str().into_owned().into_string()
     }
 
     /// Perform urlencoding into a writer
+    #[cfg(feature = "std")]
     #[inline]
     pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
-        encode_into(self.0.as_ref(), false, ascii_checker, |s| {
+        encode_into(self.0.as_ref(), false, &EncodeOptions::DEFAULT, |s| {
             writer.write_all(s.as_bytes())
         })?;
         Ok(())
@@ -49,7 +177,7 @@ impl<Str: AsRef<[u8]>> Encoded<Str> {
     /// Perform urlencoding into a string
     #[inline]
     pub fn append_to(&self, string: &mut String) {
-        append_string(self.0.as_ref(), string, false, ascii_checker);
+        append_string(self.0.as_ref(), string, false, &EncodeOptions::DEFAULT);
     }
 }
 
@@ -65,41 +193,526 @@ impl<'a> Encoded<&'a str> {
 
 impl<String: AsRef<[u8]>> fmt::Display for Encoded<String> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        encode_into(self.0.as_ref(), false, ascii_checker, |s| f.write_str(s))?;
+        encode_into(self.0.as_ref(), false, &EncodeOptions::DEFAULT, |s| f.write_str(s))?;
+        Ok(())
+    }
+}
+
+/// Same as [`Encoded`], but with a custom [`EncodeSet`] or form encoding. Created with [`Encoded::with_set`] or [`Encoded::form`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EncodedWith<Str> {
+    data: Str,
+    opts: EncodeOptions,
+}
+
+impl<Str: AsRef<[u8]>> EncodedWith<Str> {
+    /// Sets the letter case of the hex digits, which are uppercase by default.
+    ///
+    /// ```rust
+    /// use urlencoding::{Encoded, HexCase};
+    /// assert_eq!("a+%c3%a9", Encoded("a é").form().hex_case(HexCase::Lower).to_string());
+    /// ```
+    #[inline(always)]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Perform urlencoding. Returns a plain `str` for the same reason as [`encode_with`].
+    #[inline(always)]
+    pub fn to_str(&self) -> Cow<'_, str> {
+        encode_binary_internal(self.data.as_ref(), &self.opts)
+    }
+
+    /// Perform urlencoding to a string
+    #[inline]
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        self.to_str().into_owned()
+    }
+
+    /// Perform urlencoding into a writer
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_into(self.data.as_ref(), false, &self.opts, |s| {
+            writer.write_all(s.as_bytes())
+        })?;
+        Ok(())
+    }
+
+    /// Perform urlencoding into a string
+    #[inline]
+    pub fn append_to(&self, string: &mut String) {
+        append_string(self.data.as_ref(), string, false, &self.opts);
+    }
+}
+
+impl<Str: AsRef<[u8]>> fmt::Display for EncodedWith<Str> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        encode_into(self.data.as_ref(), false, &self.opts, |s| f.write_str(s))?;
+        Ok(())
+    }
+}
+
+/// Percent-encodes everything formatted into it, and writes the result to the inner [`fmt::Write`].
+///
+/// ```rust
+/// use std::fmt::Write;
+/// use urlencoding::EncodingFormatter;
+///
+/// let mut url = String::from("/items/");
+/// write!(EncodingFormatter::new(&mut url), "{}/{}", "a b", 42)?;
+/// assert_eq!(url, "/items/a%20b%2F42");
+/// # Ok::<_, std::fmt::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct EncodingFormatter<W: fmt::Write> {
+    inner: W,
+    opts: EncodeOptions,
+}
+
+impl<W: fmt::Write> EncodingFormatter<W> {
+    /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`]
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self { inner, opts: EncodeOptions::DEFAULT }
+    }
+
+    /// Percent-encodes bytes in the given set, like [`encode_with`]
+    #[inline]
+    pub fn with_set(inner: W, set: EncodeSet) -> Self {
+        Self { inner, opts: EncodeOptions::with_set(set) }
+    }
+
+    /// Encodes as `application/x-www-form-urlencoded`, like [`encode_form`]
+    #[inline]
+    pub fn form(inner: W) -> Self {
+        Self { inner, opts: EncodeOptions::FORM }
+    }
+
+    /// Encodes with the given [`EncodeOptions`]
+    #[inline]
+    pub fn with_options(inner: W, opts: EncodeOptions) -> Self {
+        Self { inner, opts }
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// The inner writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// The inner writer. Writing to it directly skips the encoding.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns the inner writer
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: fmt::Write> fmt::Write for EncodingFormatter<W> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let inner = &mut self.inner;
+        encode_into(s.as_bytes(), false, &self.opts, |s| inner.write_str(s))?;
         Ok(())
     }
 }
 
+/// Wrapper that percent-encodes the output of another `Display` type, without allocating.
+///
+/// ```rust
+/// use urlencoding::EncodedDisplay;
+/// let id = format!("/users/{}", EncodedDisplay::new(format_args!("{}:{}", "eu west", 7)));
+/// assert_eq!(id, "/users/eu%20west%3A7");
+/// ```
+///
+/// Formatting flags, like width, are not supported.
+#[derive(Copy, Clone, Debug)]
+pub struct EncodedDisplay<T> {
+    value: T,
+    opts: EncodeOptions,
+}
+
+impl<T: fmt::Display> EncodedDisplay<T> {
+    /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`]
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self { value, opts: EncodeOptions::DEFAULT }
+    }
+
+    /// Percent-encode using a custom set of bytes to escape, instead of the default one
+    #[inline]
+    #[must_use]
+    pub fn with_set(mut self, set: EncodeSet) -> Self {
+        self.opts = EncodeOptions::with_set(set).with_hex_case(self.opts.hex_case);
+        self
+    }
+
+    /// Percent-encode as `application/x-www-form-urlencoded`, like [`encode_form`]
+    #[inline]
+    #[must_use]
+    pub fn form(mut self) -> Self {
+        self.opts = EncodeOptions::FORM.with_hex_case(self.opts.hex_case);
+        self
+    }
+
+    /// Percent-encode with the given [`EncodeOptions`], which replace any set or hex case chosen before
+    #[inline]
+    #[must_use]
+    pub fn with_options(mut self, opts: EncodeOptions) -> Self {
+        self.opts = opts;
+        self
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Returns the wrapped value
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for EncodedDisplay<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::write(&mut EncodingFormatter { inner: f, opts: self.opts }, format_args!("{}", self.value))
+    }
+}
+
 /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`. Assumes UTF-8 encoding.
 ///
-/// Call `.into_owned()` if you need a `String`
+/// The [`PercentEncodedStr`] keeps track that the string is already percent-encoded.
+/// Call `.to_string()` if you need a `String`.
+///
+/// ```rust
+/// use urlencoding::encode;
+/// assert_eq!(encode("a b").as_str(), "a%20b");
+/// ```
 #[inline(always)]
 #[must_use]
-pub fn encode(data: &str) -> Cow<'_, str> {
-    encode_binary_internal(data.as_bytes(), ascii_checker)
+pub fn encode(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::DEFAULT)
+}
+
+/// Encodes as `application/x-www-form-urlencoded`, the way HTML forms do. Spaces become `+`,
+/// and every byte except alphanumerics and `*`, `-`, `.`, `_` is percent-encoded. Assumes UTF-8 encoding.
+///
+/// Use [`decode_form`](crate::decode_form) to decode it.
+///
+/// ```rust
+/// use urlencoding::encode_form;
+/// assert_eq!(encode_form("1 + 1 = 2").as_str(), "1+%2B+1+%3D+2");
+/// ```
+#[inline]
+#[must_use]
+pub fn encode_form(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::FORM)
+}
+
+/// Percent-encodes bytes that are in the given [`EncodeSet`]. Assumes UTF-8 encoding.
+///
+/// Returns a plain `str` rather than a [`PercentEncodedStr`], because a custom set (like the WHATWG sets)
+/// may leave `%` unescaped, and then the result can't be decoded back unambiguously.
+///
+/// ```rust
+/// use urlencoding::{encode_with, EncodeSet};
+/// assert_eq!(encode_with("a+b c", &EncodeSet::CONTROLS.add(b' ')), "a+b%20c");
+/// ```
+#[inline]
+#[must_use]
+pub fn encode_with<'a>(data: &'a str, set: &EncodeSet) -> Cow<'a, str> {
+    encode_binary_internal(data.as_bytes(), &EncodeOptions::with_set(*set))
+}
+
+/// Percent-encodes with the given [`EncodeOptions`]. Assumes UTF-8 encoding.
+///
+/// Returns a plain `str` for the same reason as [`encode_with`].
+///
+/// ```rust
+/// use urlencoding::{encode_with_options, EncodeOptions, HexCase};
+/// assert_eq!(encode_with_options("a/b c", &EncodeOptions::FORM.with_hex_case(HexCase::Lower)), "a%2fb+c");
+/// ```
+#[inline]
+#[must_use]
+pub fn encode_with_options<'a>(data: &'a str, opts: &EncodeOptions) -> Cow<'a, str> {
+    encode_binary_internal(data.as_bytes(), opts)
+}
+
+/// Percent-encodes a URL path, keeping `/` separators and other characters allowed in RFC 3986 paths.
+///
+/// ```rust
+/// use urlencoding::encode_path;
+/// assert_eq!(encode_path("/files/a b;v=1/?.txt").as_str(), "/files/a%20b;v=1/%3F.txt");
+/// ```
+///
+/// See [`EncodeSet::PATH`].
+#[inline]
+#[must_use]
+pub fn encode_path(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::with_set(EncodeSet::PATH))
+}
+
+/// Percent-encodes a single segment of a URL path, including any `/`.
+///
+/// ```rust
+/// use urlencoding::encode_path_segment;
+/// assert_eq!(encode_path_segment("AC/DC:live@home").as_str(), "AC%2FDC:live@home");
+/// ```
+///
+/// See [`EncodeSet::PATH_SEGMENT`].
+#[inline]
+#[must_use]
+pub fn encode_path_segment(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::with_set(EncodeSet::PATH_SEGMENT))
+}
+
+/// Percent-encodes a key or a value of a `key=value` pair in a URL query string.
+///
+/// Escapes `&`, `=`, `;` and `+`, but keeps `/`, `?` and other characters allowed in RFC 3986 queries.
+///
+/// ```rust
+/// use urlencoding::encode_query_value;
+/// assert_eq!(encode_query_value("1+1=2 & /?").as_str(), "1%2B1%3D2%20%26%20/?");
+/// ```
+///
+/// See [`EncodeSet::QUERY_VALUE`].
+#[inline]
+#[must_use]
+pub fn encode_query_value(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::with_set(EncodeSet::QUERY_VALUE))
+}
+
+/// Percent-encodes a URL fragment (the part after `#`).
+///
+/// See [`EncodeSet::FRAGMENT`].
+#[inline]
+#[must_use]
+pub fn encode_fragment(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::with_set(EncodeSet::FRAGMENT))
+}
+
+/// Percent-encodes a user name or a password in the userinfo part of a URL (`user:password@`).
+///
+/// Escapes `:` and `@`, so the user name and password need to be encoded separately.
+///
+/// See [`EncodeSet::USERINFO`].
+#[inline]
+#[must_use]
+pub fn encode_userinfo(data: &str) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data.as_bytes(), &EncodeOptions::with_set(EncodeSet::USERINFO))
 }
 
 /// The same as [encode] but allows you to specify characters to exclude from encoding.
+///
+/// Non-ASCII characters are always encoded, so they can't be excluded.
+/// For a reusable set of characters, see [`encode_with`].
+///
+/// Returns a plain `str`, because `%` can be excluded too.
 #[inline]
 #[must_use]
-pub fn encode_exclude<'a>(data: &'a str, exclude: &'a [char]) -> Cow<'a, str> {
-    encode_binary_internal(data.as_bytes(), |c| {
-        ascii_checker(c) || exclude.contains(&(**c as char))
-    })
+pub fn encode_exclude<'a>(data: &'a str, exclude: &[char]) -> Cow<'a, str> {
+    let set = exclude.iter().fold(EncodeSet::DEFAULT, |set, &c| {
+        if c.is_ascii() { set.remove(c as u8) } else { set }
+    });
+    encode_binary_internal(data.as_bytes(), &EncodeOptions::with_set(set))
 }
 
 /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`.
 #[inline]
 #[must_use]
-pub fn encode_binary(data: &[u8]) -> Cow<'_, str> {
-    encode_binary_internal(data, ascii_checker)    
+pub fn encode_binary(data: &[u8]) -> Cow<'_, PercentEncodedStr> {
+    encode_checked(data, &EncodeOptions::DEFAULT)
+}
+
+/// Percent-encodes bytes that are in the given [`EncodeSet`].
+///
+/// Returns a plain `str` for the same reason as [`encode_with`].
+#[inline]
+#[must_use]
+pub fn encode_binary_with<'a>(data: &'a [u8], set: &EncodeSet) -> Cow<'a, str> {
+    encode_binary_internal(data, &EncodeOptions::with_set(*set))
+}
+
+/// Percent-encodes with the given [`EncodeOptions`].
+///
+/// Returns a plain `str` for the same reason as [`encode_with`].
+#[inline]
+#[must_use]
+pub fn encode_binary_with_options<'a>(data: &'a [u8], opts: &EncodeOptions) -> Cow<'a, str> {
+    encode_binary_internal(data, opts)
+}
+
+/// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`. Assumes UTF-8 encoding.
+///
+/// Same as [`encode`], but reuses the `String`. It's returned untouched if nothing needs escaping,
+/// and otherwise it's grown at most once.
+///
+/// ```rust
+/// use urlencoding::encode_owned;
+/// assert_eq!(encode_owned(String::from("a b")), "a%20b");
+/// ```
+#[must_use]
+pub fn encode_owned(data: String) -> String {
+    encode_binary_owned(data.into_bytes())
+}
+
+/// Same as [`encode_owned`], but with the given [`EncodeOptions`]
+#[must_use]
+pub fn encode_owned_with_options(data: String, opts: &EncodeOptions) -> String {
+    encode_binary_owned_with_options(data.into_bytes(), opts)
+}
+
+/// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`.
+///
+/// Same as [`encode_binary`], but reuses the `Vec`. Its buffer is grown at most once.
+#[inline]
+#[must_use]
+pub fn encode_binary_owned(data: Vec<u8>) -> String {
+    encode_binary_owned_with_options(data, &EncodeOptions::DEFAULT)
+}
+
+/// Same as [`encode_binary_owned`], but with the given [`EncodeOptions`]
+#[must_use]
+pub fn encode_binary_owned_with_options(mut data: Vec<u8>, opts: &EncodeOptions) -> String {
+    let old_len = data.len();
+    let new_len = encoded_len_with_options(&data, opts);
+    if new_len != old_len {
+        data.resize(new_len, 0);
+        // encode from the end, so that the output never overwrites unread input
+        let hex = opts.hex_case.digits();
+        let mut write = new_len;
+        for read in (0..old_len).rev() {
+            let byte = data[read];
+            if byte == b' ' && opts.space_as_plus {
+                write -= 1;
+                data[write] = b'+';
+            } else if opts.set.contains(byte) {
+                write -= 3;
+                data[write..write + 3].copy_from_slice(&[b'%', hex[(byte >> 4) as usize], hex[(byte & 15) as usize]]);
+            } else {
+                write -= 1;
+                data[write] = byte;
+            }
+        }
+    } else if opts.space_as_plus {
+        // same length, but spaces still become `+`
+        for byte in &mut data {
+            if *byte == b' ' {
+                *byte = b'+';
+            }
+        }
+    }
+    unsafe {
+        // non-ASCII bytes are always escaped
+        String::from_utf8_unchecked(data)
+    }
+}
+
+impl From<Encoded<String>> for String {
+    /// Same as [`encode_owned`]
+    #[inline]
+    fn from(encoded: Encoded<String>) -> Self {
+        encode_owned(encoded.0)
+    }
+}
+
+/// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~` into the given buffer, without allocating.
+/// Returns length of the encoded data.
+///
+/// Use [`encoded_len`] to find the required size. The output can be up to 3 times longer than the input.
+/// If the buffer is too small, its contents are unspecified.
+///
+/// ```rust
+/// use urlencoding::encode_to_slice;
+///
+/// let mut buf = [0; 16];
+/// let len = encode_to_slice("a b/c".as_bytes(), &mut buf)?;
+/// assert_eq!(&buf[..len], b"a%20b%2Fc");
+/// # Ok::<_, urlencoding::BufferTooSmall>(())
+/// ```
+pub fn encode_to_slice(data: &[u8], out: &mut [u8]) -> Result<usize, BufferTooSmall> {
+    encode_to_slice_with_options(data, out, &EncodeOptions::DEFAULT)
+}
+
+/// Same as [`encode_to_slice`], but with the given [`EncodeOptions`]
+///
+/// ```rust
+/// use urlencoding::{encode_to_slice_with_options, EncodeOptions, HexCase};
+///
+/// let mut buf = [0; 16];
+/// let len = encode_to_slice_with_options("a b/c".as_bytes(), &mut buf, &EncodeOptions::FORM.with_hex_case(HexCase::Lower))?;
+/// assert_eq!(&buf[..len], b"a+b%2fc");
+/// # Ok::<_, urlencoding::BufferTooSmall>(())
+/// ```
+pub fn encode_to_slice_with_options(data: &[u8], out: &mut [u8], opts: &EncodeOptions) -> Result<usize, BufferTooSmall> {
+    let mut len = 0;
+    let res = encode_into(data, false, opts, |s| {
+        let dst = out.get_mut(len..len + s.len()).ok_or(())?;
+        dst.copy_from_slice(s.as_bytes());
+        len += s.len();
+        Ok(())
+    });
+    match res {
+        Ok(_) => Ok(len),
+        Err(()) => Err(BufferTooSmall { required: encoded_len_with_options(data, opts) }),
+    }
+}
+
+/// Length of the string that [`encode_binary`] would return, without encoding it
+#[inline]
+#[must_use]
+pub fn encoded_len(data: &[u8]) -> usize {
+    encoded_len_with_options(data, &EncodeOptions::DEFAULT)
+}
+
+/// Length of the string that [`encode_binary_with_options`] would return, without encoding it
+#[must_use]
+pub fn encoded_len_with_options(data: &[u8], opts: &EncodeOptions) -> usize {
+    let mut len = 0;
+    let _ = encode_into(data, false, opts, |s| {
+        len += s.len();
+        Ok::<_, core::convert::Infallible>(())
+    });
+    len
+}
+
+#[inline]
+/// Only for the built-in sets that escape `%` and all non-ASCII bytes
+fn encode_checked<'a>(data: &'a [u8], opts: &EncodeOptions) -> Cow<'a, PercentEncodedStr> {
+    debug_assert!(opts.set.contains(b'%'));
+    match encode_binary_internal(data, opts) {
+        Cow::Borrowed(s) => Cow::Borrowed(PercentEncodedStr::from_str_unchecked(s)),
+        Cow::Owned(s) => Cow::Owned(PercentEncodedString::from_string_unchecked(s, *opts)),
+    }
 }
 
-fn encode_binary_internal(data: &[u8], safety_checker: impl FnMut(&&u8) -> bool) -> Cow<'_, str> {
+fn encode_binary_internal<'a>(data: &'a [u8], opts: &EncodeOptions) -> Cow<'a, str> {
     // add maybe extra capacity, but try not to exceed allocator's bucket size
     let mut escaped = String::new();
     let _ = escaped.try_reserve(data.len() | 15);
-    let unmodified = append_string(data, &mut escaped, true, safety_checker);
+    let unmodified = append_string(data, &mut escaped, true, opts);
     if unmodified {
         return Cow::Borrowed(unsafe {
             // encode_into has checked it's ASCII
@@ -109,29 +722,31 @@ fn encode_binary_internal(data: &[u8], safety_checker: impl FnMut(&&u8) -> bool)
     Cow::Owned(escaped)
 }
 
-fn append_string(
+pub(crate) fn append_string(
     data: &[u8],
     escaped: &mut String,
     may_skip: bool,
-    safety_checker: impl FnMut(&&u8) -> bool,
+    opts: &EncodeOptions,
 ) -> bool {
-    encode_into(data, may_skip, safety_checker, |s| {
+    encode_into(data, may_skip, opts, |s| {
         escaped.push_str(s);
-        Ok::<_, std::convert::Infallible>(())
+        Ok::<_, core::convert::Infallible>(())
     })
     .unwrap()
 }
 
-fn encode_into<E>(
+pub(crate) fn encode_into<E>(
     mut data: &[u8],
     may_skip_write: bool,
-    mut safety_checker: impl FnMut(&&u8) -> bool,
+    opts: &EncodeOptions,
     mut push_str: impl FnMut(&str) -> Result<(), E>,
 ) -> Result<bool, E> {
+    let set = &opts.set;
+    let hex = opts.hex_case.digits();
     let mut pushed = false;
     loop {
         // Fast path to skip over safe chars at the beginning of the remaining string
-        let ascii_len = data.iter().take_while(|c| safety_checker(c)).count();
+        let ascii_len = unescaped_prefix_len(data, set);
 
         let (safe, rest) = if ascii_len >= data.len() {
             if !pushed && may_skip_write {
@@ -150,8 +765,12 @@ fn encode_into<E>(
         }
 
         match rest.split_first() {
+            Some((b' ', rest)) if opts.space_as_plus => {
+                push_str("+")?;
+                data = rest;
+            }
             Some((byte, rest)) => {
-                let enc = &[b'%', to_hex_digit(byte >> 4), to_hex_digit(byte & 15)];
+                let enc = &[b'%', hex[(byte >> 4) as usize], hex[(byte & 15) as usize]];
                 push_str(unsafe { str::from_utf8_unchecked(enc) })?;
                 data = rest;
             }
@@ -160,11 +779,3 @@ fn encode_into<E>(
     }
     Ok(false)
 }
-
-#[inline]
-fn to_hex_digit(digit: u8) -> u8 {
-    match digit {
-        0..=9 => b'0' + digit,
-        10..=255 => b'A' - 10 + digit,
-    }
-}
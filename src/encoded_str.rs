@@ -0,0 +1,472 @@
+use crate::dec::from_hex_digit;
+use crate::enc::{append_string, EncodeOptions};
+use crate::scan::unescaped_prefix_len;
+use crate::{decode, decode_binary, decode_form, DecodeError, EncodeSet, InvalidPercentEncoding};
+use alloc::borrow::{Cow, ToOwned};
+use alloc::string::String;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::{fmt, ops};
+
+/// A string slice that is known to be percent-encoded, like `str` for [`PercentEncodedString`].
+///
+/// It contains only ASCII, and every `%` in it starts a valid `%XX` escape, so it can't be encoded twice by mistake.
+/// [`encode`](crate::encode) returns it.
+///
+/// ```rust
+/// use urlencoding::{encode, EncodeSet, PercentEncodedStr};
+///
+/// let path = PercentEncodedStr::with_set("/a%20b/c", &EncodeSet::PATH)?;
+/// assert_eq!(path.decode()?, "/a b/c");
+/// assert!(PercentEncodedStr::new("100%").is_err());
+/// assert_eq!(encode("100%").as_str(), "100%25");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PercentEncodedStr(str);
+
+impl PercentEncodedStr {
+    /// Checks that everything except alphanumerics and `-`, `_`, `.`, `~` is escaped, like [`encode`](crate::encode) does
+    #[inline]
+    pub fn new(data: &str) -> Result<&Self, InvalidPercentEncoding> {
+        Self::with_set(data, &EncodeSet::DEFAULT)
+    }
+
+    /// Checks that no byte in the given set is left unescaped, and that all escapes are well-formed.
+    ///
+    /// `%` can't be unescaped, even if it's not in the set.
+    #[inline]
+    pub fn with_set<'a>(data: &'a str, set: &EncodeSet) -> Result<&'a Self, InvalidPercentEncoding> {
+        Self::with_options(data, &EncodeOptions::with_set(*set))
+    }
+
+    /// Checks that it's `application/x-www-form-urlencoded`, like [`encode_form`](crate::encode_form) makes it,
+    /// where `+` is an encoded space.
+    ///
+    /// ```rust
+    /// use urlencoding::PercentEncodedStr;
+    /// let form = PercentEncodedStr::form("a+b%2Bc")?;
+    /// assert_eq!(form.decode_form()?, "a b+c");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn form(data: &str) -> Result<&Self, InvalidPercentEncoding> {
+        Self::with_options(data, &EncodeOptions::FORM)
+    }
+
+    /// Checks that it could have been encoded with the given options, like [`with_set`](Self::with_set),
+    /// but also allows `+` if spaces are encoded as `+`.
+    pub fn with_options<'a>(data: &'a str, opts: &EncodeOptions) -> Result<&'a Self, InvalidPercentEncoding> {
+        validate(data.as_bytes(), opts)?;
+        Ok(Self::from_str_unchecked(data))
+    }
+
+    /// The caller must ensure it's percent-encoded
+    #[inline]
+    pub(crate) fn from_str_unchecked(data: &str) -> &Self {
+        unsafe {
+            // repr(transparent)
+            &*(data as *const str as *const Self)
+        }
+    }
+
+    /// The percent-encoded string
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Decodes it, like [`decode`]
+    #[inline]
+    pub fn decode(&self) -> Result<Cow<'_, str>, DecodeError> {
+        decode(&self.0)
+    }
+
+    /// Decodes it like [`decode_form`], with `+` as a space
+    #[inline]
+    pub fn decode_form(&self) -> Result<Cow<'_, str>, DecodeError> {
+        decode_form(&self.0)
+    }
+
+    /// Decodes it as binary data, like [`decode_binary`]
+    #[inline]
+    #[must_use]
+    pub fn decode_binary(&self) -> Cow<'_, [u8]> {
+        decode_binary(self.0.as_bytes())
+    }
+}
+
+impl ops::Deref for PercentEncodedStr {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for PercentEncodedStr {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for PercentEncodedStr {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl fmt::Display for PercentEncodedStr {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ToOwned for PercentEncodedStr {
+    type Owned = PercentEncodedString;
+
+    /// The copy appends with the default options in [`push_raw`](PercentEncodedString::push_raw)
+    #[inline]
+    fn to_owned(&self) -> PercentEncodedString {
+        PercentEncodedString::from_string_unchecked(self.0.to_owned(), EncodeOptions::DEFAULT)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for &'a PercentEncodedStr {
+    type Error = InvalidPercentEncoding;
+
+    /// Same as [`PercentEncodedStr::new`]
+    #[inline]
+    fn try_from(data: &'a str) -> Result<Self, Self::Error> {
+        PercentEncodedStr::new(data)
+    }
+}
+
+/// An owned string that is known to be percent-encoded. See [`PercentEncodedStr`].
+///
+/// It remembers the set it was checked with, and [`push_raw`](Self::push_raw) encodes with it.
+///
+/// ```rust
+/// use urlencoding::{encode, EncodeSet, PercentEncodedStr, PercentEncodedString};
+///
+/// let mut key = encode("Zoë & co").into_owned();
+/// key.push_encoded(PercentEncodedStr::new("%2Finvoice%201")?);
+/// key.push_raw(".pdf");
+/// assert_eq!(key.as_str(), "Zo%C3%AB%20%26%20co%2Finvoice%201.pdf");
+///
+/// let mut path = PercentEncodedString::with_set("/files".into(), &EncodeSet::PATH)?;
+/// path.push_raw("/a b");
+/// assert_eq!(path, "/files/a%20b");
+/// # Ok::<_, urlencoding::InvalidPercentEncoding>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PercentEncodedString {
+    string: String,
+    /// Used by `push_raw`. Its set always has `%`.
+    opts: EncodeOptions,
+}
+
+impl PercentEncodedString {
+    /// An empty string, that appends like [`encode`](crate::encode)
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::from_string_unchecked(String::new(), EncodeOptions::DEFAULT)
+    }
+
+    /// Checks the string like [`PercentEncodedStr::with_set`], without copying it
+    #[inline]
+    pub fn with_set(data: String, set: &EncodeSet) -> Result<Self, InvalidPercentEncoding> {
+        Self::with_options(data, &EncodeOptions::with_set(*set))
+    }
+
+    /// Checks the string like [`PercentEncodedStr::form`], without copying it
+    #[inline]
+    pub fn form(data: String) -> Result<Self, InvalidPercentEncoding> {
+        Self::with_options(data, &EncodeOptions::FORM)
+    }
+
+    /// Checks the string like [`PercentEncodedStr::with_options`], without copying it
+    pub fn with_options(data: String, opts: &EncodeOptions) -> Result<Self, InvalidPercentEncoding> {
+        validate(data.as_bytes(), opts)?;
+        Ok(Self::from_string_unchecked(data, *opts))
+    }
+
+    /// The caller must ensure it's percent-encoded
+    #[inline]
+    pub(crate) fn from_string_unchecked(string: String, mut opts: EncodeOptions) -> Self {
+        opts.set = opts.set.add(b'%');
+        Self { string, opts }
+    }
+
+    /// The percent-encoded string
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// Borrows it as a [`PercentEncodedStr`]
+    #[inline]
+    #[must_use]
+    pub fn as_percent_encoded_str(&self) -> &PercentEncodedStr {
+        PercentEncodedStr::from_str_unchecked(&self.string)
+    }
+
+    /// Returns the percent-encoded `String`
+    #[inline]
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.string
+    }
+
+    /// Appends a string that is already percent-encoded, as-is
+    #[inline]
+    pub fn push_encoded(&mut self, encoded: &PercentEncodedStr) {
+        self.string.push_str(&encoded.0);
+    }
+
+    /// Percent-encodes the string with the set and options this string was checked with, and appends it.
+    /// `%` is always escaped.
+    #[inline]
+    pub fn push_raw(&mut self, raw: &str) {
+        append_string(raw.as_bytes(), &mut self.string, false, &self.opts);
+    }
+}
+
+impl Default for PercentEncodedString {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Compares only the strings, like `Borrow` requires
+
+impl PartialEq for PercentEncodedString {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.string == other.string
+    }
+}
+
+impl Eq for PercentEncodedString {}
+
+impl PartialOrd for PercentEncodedString {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PercentEncodedString {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.string.cmp(&other.string)
+    }
+}
+
+impl Hash for PercentEncodedString {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.string.hash(state);
+    }
+}
+
+macro_rules! impl_eq_str {
+    ($($encoded:ty, $other:ty;)*) => {$(
+        impl<'a> PartialEq<$other> for $encoded {
+            #[inline]
+            fn eq(&self, other: &$other) -> bool {
+                self.as_str() == &other[..]
+            }
+        }
+
+        impl<'a> PartialEq<$encoded> for $other {
+            #[inline]
+            fn eq(&self, other: &$encoded) -> bool {
+                &self[..] == other.as_str()
+            }
+        }
+    )*};
+}
+
+impl_eq_str! {
+    PercentEncodedStr, str;
+    PercentEncodedStr, &'a str;
+    PercentEncodedStr, String;
+    PercentEncodedString, str;
+    PercentEncodedString, &'a str;
+    PercentEncodedString, String;
+}
+
+impl ops::Deref for PercentEncodedString {
+    type Target = PercentEncodedStr;
+
+    #[inline]
+    fn deref(&self) -> &PercentEncodedStr {
+        self.as_percent_encoded_str()
+    }
+}
+
+impl Borrow<PercentEncodedStr> for PercentEncodedString {
+    #[inline]
+    fn borrow(&self) -> &PercentEncodedStr {
+        self.as_percent_encoded_str()
+    }
+}
+
+impl Borrow<str> for PercentEncodedString {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.string
+    }
+}
+
+impl AsRef<str> for PercentEncodedString {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl AsRef<[u8]> for PercentEncodedString {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.string.as_bytes()
+    }
+}
+
+impl fmt::Display for PercentEncodedString {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.string)
+    }
+}
+
+impl TryFrom<String> for PercentEncodedString {
+    type Error = InvalidPercentEncoding;
+
+    /// Same as [`PercentEncodedStr::new`]
+    #[inline]
+    fn try_from(data: String) -> Result<Self, Self::Error> {
+        Self::with_set(data, &EncodeSet::DEFAULT)
+    }
+}
+
+impl From<&PercentEncodedStr> for PercentEncodedString {
+    #[inline]
+    fn from(encoded: &PercentEncodedStr) -> Self {
+        encoded.to_owned()
+    }
+}
+
+impl From<PercentEncodedString> for String {
+    #[inline]
+    fn from(encoded: PercentEncodedString) -> Self {
+        encoded.string
+    }
+}
+
+fn validate(mut data: &[u8], opts: &EncodeOptions) -> Result<(), InvalidPercentEncoding> {
+    // in form encoding `+` is an encoded space
+    let set = if opts.space_as_plus { opts.set.remove(b'+') } else { opts.set }.add(b'%');
+    let len = data.len();
+    loop {
+        data = &data[unescaped_prefix_len(data, &set)..];
+        match *data {
+            [] => return Ok(()),
+            [b'%', first, second, ..] if from_hex_digit(first).is_some() && from_hex_digit(second).is_some() => {
+                data = &data[3..];
+            },
+            _ => return Err(InvalidPercentEncoding { offset: len - data.len() }),
+        }
+    }
+}
+
+#[test]
+fn validates() {
+    assert_eq!(PercentEncodedStr::new("").unwrap().as_str(), "");
+    assert_eq!(PercentEncodedStr::new("a-b_c.d~%20%2f").unwrap().as_str(), "a-b_c.d~%20%2f");
+    for (data, offset) in [("a b", 1), ("ab/", 2), ("%", 0), ("x%2", 1), ("%zz", 0), ("%2%20", 0), ("ö", 0)] {
+        assert_eq!(PercentEncodedStr::new(data).unwrap_err().offset(), offset, "{data}");
+    }
+
+    assert!(PercentEncodedStr::with_set("/a/b", &EncodeSet::PATH).is_ok());
+    assert!(PercentEncodedStr::with_set("a?b", &EncodeSet::PATH).is_err());
+    assert!(PercentEncodedStr::with_set("50% off", &EncodeSet::EMPTY).is_err());
+    assert!(PercentEncodedString::with_set("a b".into(), &EncodeSet::CONTROLS).is_ok());
+    assert!(PercentEncodedString::try_from(String::from("a b")).is_err());
+    assert!(<&PercentEncodedStr>::try_from("%41").is_ok());
+
+    assert_eq!(PercentEncodedStr::form("a+b%2B").unwrap().decode_form().unwrap(), "a b+");
+    assert_eq!(PercentEncodedStr::form("a b").unwrap_err().offset(), 1);
+    assert!(PercentEncodedStr::with_set("a+b", &EncodeSet::WHATWG_FORM).is_err());
+    assert!(PercentEncodedStr::with_options("a+b", &EncodeOptions::FORM).is_ok());
+    assert!(PercentEncodedString::form(crate::encode_form("1 + 1").into_owned().into_string()).is_ok());
+}
+
+#[test]
+fn builds_strings() {
+    use std::collections::HashSet;
+
+    let mut s = PercentEncodedString::new();
+    s.push_raw("a b/");
+    s.push_encoded(PercentEncodedStr::new("%2F").unwrap());
+    s.push_raw("100%");
+    assert_eq!(s.as_str(), "a%20b%2F%2F100%25");
+    assert_eq!(s.to_string(), s.as_str());
+    assert_eq!(s.decode().unwrap(), "a b//100%");
+    assert_eq!(&*s.decode_binary(), b"a b//100%");
+    assert_eq!(s.len(), 17);
+    assert_eq!(String::from(s.clone()), s.as_str());
+
+    let borrowed: &PercentEncodedStr = &s;
+    assert_eq!(borrowed.to_owned(), s);
+    assert!(PercentEncodedStr::new(&s).is_ok());
+
+    let set: HashSet<PercentEncodedString> = [s.clone()].into_iter().collect();
+    assert!(set.contains(borrowed));
+    assert!(set.contains("a%20b%2F%2F100%25"));
+    assert_eq!(crate::encode("ö").into_owned().into_string(), "%C3%B6");
+    assert_eq!(s, "a%20b%2F%2F100%25");
+    assert_eq!("a%20b%2F%2F100%25", *borrowed);
+}
+
+#[test]
+fn appends_with_the_checked_options() {
+    use crate::HexCase;
+    use std::collections::hash_map::DefaultHasher;
+
+    let mut path = PercentEncodedString::with_set("/a".into(), &EncodeSet::PATH).unwrap();
+    path.push_raw("/b c");
+    assert_eq!(path, "/a/b%20c");
+
+    let mut form = PercentEncodedString::form(String::new()).unwrap();
+    form.push_raw("a b+");
+    assert_eq!(form, "a+b%2B");
+
+    // `%` is escaped even if the set doesn't have it
+    let mut lower = PercentEncodedString::with_options("".into(), &EncodeOptions::with_set(EncodeSet::CONTROLS).with_hex_case(HexCase::Lower)).unwrap();
+    lower.push_raw("50% ö");
+    assert_eq!(lower, "50%25 %c3%b6");
+
+    // options don't affect equality, so that it's consistent with `Borrow<str>`
+    let hash = |s: &PercentEncodedString| {
+        let mut hasher = DefaultHasher::new();
+        s.hash(&mut hasher);
+        hasher.finish()
+    };
+    let (a, b) = (PercentEncodedString::form("a".into()).unwrap(), PercentEncodedString::try_from(String::from("a")).unwrap());
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(a.cmp(&b), Ordering::Equal);
+    assert_eq!(a.to_owned(), PercentEncodedStr::new("a").unwrap().to_owned());
+}
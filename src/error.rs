@@ -0,0 +1,139 @@
+use alloc::string::{FromUtf8Error, String};
+use alloc::vec::Vec;
+use core::{error, fmt, str};
+
+/// Error returned by all the fallible decoding functions, such as [`decode`](crate::decode) and [`decode_strict`](crate::decode_strict)
+///
+/// Offsets are in bytes, and always refer to the percent-encoded input, not the decoded output.
+///
+/// ## Migrating from `FromUtf8Error`
+///
+/// Previous versions of [`decode`](crate::decode) returned `std::string::FromUtf8Error`.
+/// [`into_bytes`](Self::into_bytes), [`as_bytes`](Self::as_bytes) and [`utf8_error`](Self::utf8_error)
+/// work the same way, and [`into_from_utf8_error`](Self::into_from_utf8_error) gives the old error type back.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// `%` at `offset` is not followed by two characters
+    TruncatedEscape { offset: usize },
+    /// `%` at `offset` is followed by a character that isn't a hex digit
+    InvalidHexDigit { offset: usize },
+    /// Decoded bytes aren't valid UTF-8. The invalid sequence starts at `offset`.
+    ///
+    /// `decoded` is the complete decoded output, including the invalid bytes.
+    InvalidUtf8 { offset: usize, decoded: Vec<u8> },
+}
+
+impl DecodeError {
+    /// Position in the encoded input where the error has been found
+    #[inline]
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        match *self {
+            Self::TruncatedEscape { offset } |
+            Self::InvalidHexDigit { offset } |
+            Self::InvalidUtf8 { offset, .. } => offset,
+        }
+    }
+
+    /// Decoded bytes that aren't valid UTF-8, without copying them.
+    ///
+    /// Returns an empty `Vec` if the error isn't [`DecodeError::InvalidUtf8`].
+    #[inline]
+    #[must_use]
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::InvalidUtf8 { decoded, .. } => decoded,
+            _ => Vec::new(),
+        }
+    }
+
+    /// Decoded bytes that aren't valid UTF-8.
+    ///
+    /// Returns an empty slice if the error isn't [`DecodeError::InvalidUtf8`].
+    #[inline]
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::InvalidUtf8 { decoded, .. } => decoded,
+            _ => &[],
+        }
+    }
+
+    /// Details about the invalid UTF-8 sequence, relative to the decoded bytes
+    #[must_use]
+    pub fn utf8_error(&self) -> Option<str::Utf8Error> {
+        str::from_utf8(self.as_bytes()).err()
+    }
+
+    /// Converts to the error type returned by the previous versions of [`decode`](crate::decode).
+    ///
+    /// Returns `None` if the error isn't [`DecodeError::InvalidUtf8`].
+    #[must_use]
+    pub fn into_from_utf8_error(self) -> Option<FromUtf8Error> {
+        match self {
+            Self::InvalidUtf8 { decoded, .. } => String::from_utf8(decoded).err(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::TruncatedEscape { offset } => write!(f, "incomplete percent-escape at {offset}"),
+            Self::InvalidHexDigit { offset } => write!(f, "invalid hex digit in percent-escape at {offset}"),
+            Self::InvalidUtf8 { offset, .. } => write!(f, "invalid UTF-8 at {offset}"),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+/// Error returned by [`encode_to_slice`](crate::encode_to_slice) and [`decode_to_slice`](crate::decode_to_slice)
+/// when the output doesn't fit in the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferTooSmall {
+    pub(crate) required: usize,
+}
+
+impl BufferTooSmall {
+    /// Length of the buffer that would have been large enough for the complete output
+    #[inline]
+    #[must_use]
+    pub fn required(&self) -> usize {
+        self.required
+    }
+}
+
+impl fmt::Display for BufferTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "output buffer too small, needs {} bytes", self.required)
+    }
+}
+
+impl error::Error for BufferTooSmall {}
+
+/// Error returned when creating a [`PercentEncodedStr`](crate::PercentEncodedStr) or [`PercentEncodedString`](crate::PercentEncodedString)
+/// from a string that isn't percent-encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidPercentEncoding {
+    pub(crate) offset: usize,
+}
+
+impl InvalidPercentEncoding {
+    /// Position of a malformed escape, or of a character that should have been escaped
+    #[inline]
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for InvalidPercentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid percent-encoding at {}", self.offset)
+    }
+}
+
+impl error::Error for InvalidPercentEncoding {}
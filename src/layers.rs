@@ -0,0 +1,171 @@
+use crate::dec::{encoded_offset, from_hex_digit};
+use crate::scan::literal_len;
+use crate::{decode_binary, DecodeError};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Checks whether the string is percent-encoded more than once, i.e. decoding it once leaves valid escapes,
+/// like `%252F` that decodes to `%2F`.
+///
+/// ```rust
+/// use urlencoding::is_double_encoded;
+/// assert!(is_double_encoded("%252e%252e%252f"));
+/// assert!(!is_double_encoded("%2e%2e%2f"));
+/// assert!(!is_double_encoded("100%25"));
+/// ```
+#[must_use]
+pub fn is_double_encoded(data: &str) -> bool {
+    let data = data.as_bytes();
+    has_escape(data) && has_escape(&decode_binary(data))
+}
+
+/// Decodes the string repeatedly, until it has no valid escapes left, or `max_rounds` is reached.
+///
+/// Decoding once with [`decode`](crate::decode) is almost always what you want. This is for checking
+/// what the input could become if it was decoded again somewhere else.
+///
+/// Fails only with [`DecodeError::InvalidUtf8`], if the final result isn't valid UTF-8.
+/// Its offset is in the original input.
+///
+/// ```rust
+/// use urlencoding::decode_fully;
+///
+/// let report = decode_fully("%252e%252e%252f", 5)?;
+/// assert_eq!(report.decoded(), "../");
+/// assert_eq!(report.layers(), 2);
+/// assert!(report.is_fully_decoded());
+/// # Ok::<_, urlencoding::DecodeError>(())
+/// ```
+pub fn decode_fully(data: &str, max_rounds: usize) -> Result<DecodeReport<'_>, DecodeError> {
+    let mut decoded = Cow::Borrowed(data.as_bytes());
+    let mut layers = 0;
+    let mut fully_decoded = true;
+    while has_escape(&decoded) {
+        if layers >= max_rounds {
+            fully_decoded = false;
+            break;
+        }
+        decoded = Cow::Owned(decode_binary(&decoded).into_owned());
+        layers += 1;
+    }
+
+    let decoded = match decoded {
+        Cow::Borrowed(_) => Cow::Borrowed(data),
+        Cow::Owned(bytes) => Cow::Owned(String::from_utf8(bytes).map_err(|e| {
+            let offset = invalid_utf8_offset(data.as_bytes(), layers, e.utf8_error().valid_up_to());
+            DecodeError::InvalidUtf8 { offset, decoded: e.into_bytes() }
+        })?),
+    };
+    Ok(DecodeReport { decoded, layers, fully_decoded })
+}
+
+/// Result of [`decode_fully`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecodeReport<'a> {
+    decoded: Cow<'a, str>,
+    layers: usize,
+    fully_decoded: bool,
+}
+
+impl<'a> DecodeReport<'a> {
+    /// The decoded string
+    #[inline]
+    #[must_use]
+    pub fn decoded(&self) -> &str {
+        &self.decoded
+    }
+
+    /// The decoded string, borrowed from the input if it had no escapes
+    #[inline]
+    #[must_use]
+    pub fn into_decoded(self) -> Cow<'a, str> {
+        self.decoded
+    }
+
+    /// How many times the input has been decoded. 0 if it had no escapes, and 2 or more if it was double-encoded.
+    #[inline]
+    #[must_use]
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    /// Whether the input has been percent-encoded more than once
+    #[inline]
+    #[must_use]
+    pub fn is_double_encoded(&self) -> bool {
+        self.layers > 1
+    }
+
+    /// `false` if `max_rounds` has been reached, and the result still has escapes to decode
+    #[inline]
+    #[must_use]
+    pub fn is_fully_decoded(&self) -> bool {
+        self.fully_decoded
+    }
+}
+
+/// Whether there's any `%` followed by two hex digits
+fn has_escape(mut data: &[u8]) -> bool {
+    loop {
+        data = &data[literal_len(data, false)..];
+        match *data {
+            [] => return false,
+            [b'%', first, second, ..] if from_hex_digit(first).is_some() && from_hex_digit(second).is_some() => return true,
+            [_, ref rest @ ..] => data = rest,
+        }
+    }
+}
+
+/// Maps position in the output of the last layer back to the input, by decoding the layers again
+#[cold]
+fn invalid_utf8_offset(data: &[u8], layers: usize, mut offset: usize) -> usize {
+    let mut inputs = Vec::with_capacity(layers);
+    inputs.push(Cow::Borrowed(data));
+    for _ in 1..layers {
+        let next = decode_binary(&inputs[inputs.len() - 1]).into_owned();
+        inputs.push(Cow::Owned(next));
+    }
+    for input in inputs.iter().rev() {
+        offset = encoded_offset(input, offset);
+    }
+    offset
+}
+
+#[test]
+fn detects_double_encoding() {
+    for data in ["%252e", "%25252F", "a%2541b", "%%34%31", "%2525"] {
+        assert!(is_double_encoded(data), "{data}");
+    }
+    for data in ["", "abc", "%2e%2f", "%25", "%25zz", "%252", "%zz%25", "100%"] {
+        assert!(!is_double_encoded(data), "{data}");
+    }
+}
+
+#[test]
+fn decodes_fully() {
+    let report = decode_fully("plain", 3).unwrap();
+    assert!(matches!(report.clone().into_decoded(), Cow::Borrowed("plain")));
+    assert_eq!((report.layers(), report.is_fully_decoded(), report.is_double_encoded()), (0, true, false));
+
+    let report = decode_fully("a%20b%zz", 3).unwrap();
+    assert_eq!((report.decoded(), report.layers(), report.is_double_encoded()), ("a b%zz", 1, false));
+
+    let report = decode_fully("%25252e%25252e%25252f", 3).unwrap();
+    assert_eq!((report.decoded(), report.layers(), report.is_fully_decoded()), ("../", 3, true));
+    assert!(report.is_double_encoded());
+
+    let report = decode_fully("%25252e", 2).unwrap();
+    assert_eq!((report.decoded(), report.layers(), report.is_fully_decoded()), ("%2e", 2, false));
+
+    let report = decode_fully("%2541", 0).unwrap();
+    assert_eq!((report.decoded(), report.layers(), report.is_fully_decoded()), ("%2541", 0, false));
+
+    // invalid UTF-8 in the middle layer is fine
+    let report = decode_fully("%C3%25A9", 5).unwrap();
+    assert_eq!((report.decoded(), report.layers()), ("é", 2));
+
+    let err = decode_fully("ok%2541%25FF", 5).unwrap_err();
+    assert_eq!(err.offset(), 7);
+    assert_eq!(err.as_bytes(), b"okA\xFF");
+}
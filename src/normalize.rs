@@ -0,0 +1,229 @@
+use crate::dec::from_hex_digit;
+use crate::scan::literal_len;
+use crate::EncodeSet;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::hash::Hasher;
+
+/// Normalizes percent-encoding as described in [RFC 3986 section 6.2.2](https://www.rfc-editor.org/rfc/rfc3986#section-6.2.2),
+/// so that equivalent URLs compare equal.
+///
+/// Hex digits in escapes are uppercased, and escapes of unreserved characters (alphanumerics and `-`, `.`, `_`, `~`) are decoded.
+/// Escapes of all other bytes, malformed escapes, and the unescaped characters are kept as-is.
+/// Escaped hex digits right after a malformed escape stay escaped, so that `%2%41` doesn't become `%2A`.
+///
+/// Returns `Cow::Borrowed` if the string is already normalized.
+///
+/// ```rust
+/// use urlencoding::normalize;
+/// assert_eq!(normalize("%7euser/a%2fb%20c"), "~user/a%2Fb%20c");
+/// ```
+#[must_use]
+pub fn normalize(data: &str) -> Cow<'_, str> {
+    let bytes = data.as_bytes();
+    let mut same_len = literal_len(bytes, false);
+    if same_len >= bytes.len() {
+        return Cow::Borrowed(data);
+    }
+
+    // the output is the same as the input up to the first changed byte
+    let mut normalized = NormalizedBytes::new(&bytes[same_len..]);
+    let first_changed = loop {
+        match normalized.next() {
+            Some(c) if c == bytes[same_len] => same_len += 1,
+            Some(c) => break c,
+            None => return Cow::Borrowed(data),
+        }
+    };
+
+    let mut out = String::with_capacity(bytes.len());
+    out.push_str(&data[..same_len]);
+    let mut out = out.into_bytes();
+    out.push(first_changed);
+    out.extend(normalized);
+    Cow::Owned(unsafe {
+        // only ASCII is decoded, and everything else is copied from the str unchanged
+        String::from_utf8_unchecked(out)
+    })
+}
+
+/// Compares two strings as if they were [`normalize`]d, without allocating.
+///
+/// ```rust
+/// use urlencoding::eq_normalized;
+/// assert!(eq_normalized("/%7Euser", "/~user"));
+/// assert!(eq_normalized("a%2fb", "a%2Fb"));
+/// assert!(!eq_normalized("a%2Fb", "a/b"));
+/// ```
+#[must_use]
+pub fn eq_normalized(a: &str, b: &str) -> bool {
+    a == b || NormalizedBytes::new(a.as_bytes()).eq(NormalizedBytes::new(b.as_bytes()))
+}
+
+/// Feeds the [`normalize`]d form of the string to the hasher, without allocating.
+///
+/// Strings that are [`eq_normalized`] give the same hash, so it can be used to implement `Hash` for a URL key
+/// that implements `PartialEq` with [`eq_normalized`].
+///
+/// ```rust
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::Hasher;
+/// use urlencoding::hash_normalized;
+///
+/// let hash = |url| {
+///     let mut hasher = DefaultHasher::new();
+///     hash_normalized(url, &mut hasher);
+///     hasher.finish()
+/// };
+/// assert_eq!(hash("%7e%2f"), hash("~%2F"));
+/// ```
+pub fn hash_normalized<H: Hasher>(data: &str, state: &mut H) {
+    // the bytes are written in fixed-size blocks, so that it doesn't matter where the escapes were
+    let mut buf = [0; 64];
+    let mut len = 0;
+    for c in NormalizedBytes::new(data.as_bytes()) {
+        buf[len] = c;
+        len += 1;
+        if len == buf.len() {
+            state.write(&buf);
+            len = 0;
+        }
+    }
+    state.write(&buf[..len]);
+    // like str, so that hashes of "ab","c" and "a","bc" differ
+    state.write_u8(0xFF);
+}
+
+/// Iterator over bytes of the normalized data
+#[derive(Debug, Clone)]
+struct NormalizedBytes<'a> {
+    rest: &'a [u8],
+    /// Uppercased hex digits of a kept escape, in reverse order
+    pending: [u8; 2],
+    pending_len: usize,
+    /// Number of bytes of a malformed escape at the end of the output so far, like `%` or `%2`,
+    /// that a decoded hex digit would complete
+    incomplete_len: usize,
+}
+
+impl<'a> NormalizedBytes<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Self { rest: data, pending: [0; 2], pending_len: 0, incomplete_len: 0 }
+    }
+}
+
+impl Iterator for NormalizedBytes<'_> {
+    type Item = u8;
+
+    #[inline]
+    fn next(&mut self) -> Option<u8> {
+        if self.pending_len > 0 {
+            self.pending_len -= 1;
+            return Some(self.pending[self.pending_len]);
+        }
+        let (&c, rest) = self.rest.split_first()?;
+        self.rest = rest;
+        if c == b'%' {
+            if let [first, second, ..] = *rest {
+                if let (Some(first_val), Some(second_val)) = (from_hex_digit(first), from_hex_digit(second)) {
+                    self.rest = &rest[2..];
+                    let byte = (first_val << 4) | second_val;
+                    let completes_escape = self.incomplete_len > 0 && byte.is_ascii_hexdigit();
+                    self.incomplete_len = 0;
+                    if !EncodeSet::DEFAULT.contains(byte) && !completes_escape {
+                        return Some(byte);
+                    }
+                    self.pending = [second.to_ascii_uppercase(), first.to_ascii_uppercase()];
+                    self.pending_len = 2;
+                    return Some(c);
+                }
+            }
+            self.incomplete_len = 1;
+            return Some(c);
+        }
+        self.incomplete_len = if self.incomplete_len == 1 && c.is_ascii_hexdigit() { 2 } else { 0 };
+        Some(c)
+    }
+}
+
+#[test]
+fn normalizes() {
+    assert_eq!(normalize(""), "");
+    assert_eq!(normalize("%7e%7E~"), "~~~");
+    assert_eq!(normalize("%41%5a%30%2D%2e%5F"), "AZ0-._");
+    assert_eq!(normalize("%2f%3A%c3%a9"), "%2F%3A%C3%A9");
+    assert_eq!(normalize("%zz%2%"), "%zz%2%");
+    assert_eq!(normalize("%%7e"), "%~");
+    // decoding these would complete the malformed escape before them
+    assert_eq!(normalize("%2%41"), "%2%41");
+    assert_eq!(normalize("%%37%65"), "%%37e");
+    assert_eq!(normalize("%2%41%42"), "%2%41B");
+    assert_eq!(normalize("ö%7e ö"), "ö~ ö");
+
+    for data in ["abc", "a/b?c", "%2F%20", "ö%C3%B6", "%zz", "100%"] {
+        assert!(matches!(normalize(data), Cow::Borrowed(_)), "{data}");
+    }
+    assert!(matches!(normalize("%2F%2f"), Cow::Owned(_)));
+}
+
+#[test]
+fn compares_and_hashes() {
+    use std::collections::hash_map::DefaultHasher;
+
+    let hash = |data: &str| {
+        let mut hasher = DefaultHasher::new();
+        hash_normalized(data, &mut hasher);
+        hasher.finish()
+    };
+    let long = "x".repeat(100);
+    let (long_escaped, long_decoded) = (format!("{long}%41{long}"), format!("{long}A{long}"));
+    let equal = [
+        ("%7e", "~"),
+        ("%7e", "%7E"),
+        ("a%2fb", "a%2Fb"),
+        ("%61%62%63", "abc"),
+        ("%zz", "%zz"),
+        (&long_escaped, &long_decoded),
+    ];
+    for (a, b) in equal {
+        assert!(eq_normalized(a, b), "{a} {b}");
+        assert!(eq_normalized(b, a), "{b} {a}");
+        assert_eq!(hash(a), hash(b), "{a} {b}");
+        assert_eq!(normalize(a), normalize(b));
+    }
+    for (a, b) in [("%2F", "/"), ("%20", " "), ("%7e", "%7f"), ("%zz", "%ZZ"), ("a", "a%"), ("%2%41", "%2A"), ("%%37%65", "%7e")] {
+        assert!(!eq_normalized(a, b), "{a} {b}");
+        assert_ne!(hash(a), hash(b), "{a} {b}");
+    }
+}
+
+#[test]
+fn normalized_is_stable() {
+    use crate::decode_binary;
+    use std::collections::hash_map::DefaultHasher;
+
+    let hash = |data: &str| {
+        let mut hasher = DefaultHasher::new();
+        hash_normalized(data, &mut hasher);
+        hasher.finish()
+    };
+    // every string up to 5 bytes long made of these
+    let alphabet = b"%2417eAz~";
+    let mut data = Vec::new();
+    for len in 0..=5u32 {
+        for mut n in 0..alphabet.len().pow(len) {
+            data.clear();
+            for _ in 0..len {
+                data.push(alphabet[n % alphabet.len()]);
+                n /= alphabet.len();
+            }
+            let data = std::str::from_utf8(&data).unwrap();
+            let normalized = normalize(data);
+            assert_eq!(normalize(&normalized), normalized, "{data}");
+            assert!(eq_normalized(data, &normalized), "{data}");
+            assert_eq!(hash(data), hash(&normalized), "{data}");
+            assert_eq!(decode_binary(normalized.as_bytes()), decode_binary(data.as_bytes()), "{data}");
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::dec::{decode_binary_internal, into_string_lossy};
+use crate::enc::{encode_into, EncodeOptions};
+use crate::{EncodeSet, HexCase};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::{fmt, str};
+
+/// Parses a query string into decoded `(key, value)` pairs.
+///
+/// Pairs are separated by `&` (and optionally `;`). A pair without `=` has an empty value,
+/// and empty pairs are skipped. The query should not include the leading `?`.
+///
+/// By default `+` is decoded as a space, the way browsers encode forms and `URLSearchParams`.
+/// Invalid UTF-8 is replaced with `�`. Keys and values that don't contain any escapes are borrowed from the input.
+///
+/// ```rust
+/// use urlencoding::parse_query;
+///
+/// let pairs: Vec<_> = parse_query("a=1&b=two%20words&c").collect();
+/// assert_eq!(pairs, [("a".into(), "1".into()), ("b".into(), "two words".into()), ("c".into(), "".into())]);
+///
+/// let mut pairs = parse_query("a+b=c+d;e").plus_as_space(false).semicolon_separators(true);
+/// assert_eq!(pairs.next(), Some(("a+b".into(), "c+d".into())));
+/// assert_eq!(pairs.next(), Some(("e".into(), "".into())));
+/// ```
+#[inline]
+pub fn parse_query(query: &str) -> QueryPairs<'_> {
+    QueryPairs(parse_query_binary(query.as_bytes()))
+}
+
+/// Parses a query string into `(key, value)` pairs of bytes, in any encoding.
+///
+/// Same as [`parse_query`], but doesn't interpret the keys and values as UTF-8.
+#[inline]
+pub fn parse_query_binary(query: &[u8]) -> QueryPairsBinary<'_> {
+    QueryPairsBinary {
+        rest: query,
+        plus_as_space: true,
+        semicolons: false,
+    }
+}
+
+/// Iterator of decoded `(key, value)` pairs. Created by [`parse_query`].
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct QueryPairs<'a>(QueryPairsBinary<'a>);
+
+impl QueryPairs<'_> {
+    /// Whether to decode `+` as a space. This is the default. If disabled, only `%20` is a space.
+    #[inline]
+    pub fn plus_as_space(self, yes: bool) -> Self {
+        Self(self.0.plus_as_space(yes))
+    }
+
+    /// Whether `;` separates pairs, in addition to `&`. Disabled by default.
+    #[inline]
+    pub fn semicolon_separators(self, yes: bool) -> Self {
+        Self(self.0.semicolon_separators(yes))
+    }
+}
+
+impl<'a> Iterator for QueryPairs<'a> {
+    type Item = (Cow<'a, str>, Cow<'a, str>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.0.next()?;
+        Some((to_str_lossy(key), to_str_lossy(value)))
+    }
+}
+
+/// Bytes decoded from a `&str`
+fn to_str_lossy(bytes: Cow<'_, [u8]>) -> Cow<'_, str> {
+    match bytes {
+        Cow::Borrowed(b) => Cow::Borrowed(unsafe {
+            // the input was a str, and pairs are split only on ASCII bytes
+            str::from_utf8_unchecked(b)
+        }),
+        Cow::Owned(b) => Cow::Owned(into_string_lossy(b)),
+    }
+}
+
+/// Iterator of decoded `(key, value)` pairs of bytes. Created by [`parse_query_binary`].
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct QueryPairsBinary<'a> {
+    rest: &'a [u8],
+    plus_as_space: bool,
+    semicolons: bool,
+}
+
+impl QueryPairsBinary<'_> {
+    /// Whether to decode `+` as a space. This is the default. If disabled, only `%20` is a space.
+    #[inline]
+    pub fn plus_as_space(mut self, yes: bool) -> Self {
+        self.plus_as_space = yes;
+        self
+    }
+
+    /// Whether `;` separates pairs, in addition to `&`. Disabled by default.
+    #[inline]
+    pub fn semicolon_separators(mut self, yes: bool) -> Self {
+        self.semicolons = yes;
+        self
+    }
+}
+
+impl<'a> Iterator for QueryPairsBinary<'a> {
+    type Item = (Cow<'a, [u8]>, Cow<'a, [u8]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.rest.is_empty() {
+                return None;
+            }
+            let semicolons = self.semicolons;
+            let mut parts = self.rest.splitn(2, |&c| c == b'&' || (semicolons && c == b';'));
+            let pair = parts.next().unwrap_or_default();
+            self.rest = parts.next().unwrap_or_default();
+            if pair.is_empty() {
+                continue;
+            }
+
+            let mut parts = pair.splitn(2, |&c| c == b'=');
+            let key = parts.next().unwrap_or_default();
+            let value = parts.next().unwrap_or_default();
+            return Some((
+                decode_binary_internal(key, self.plus_as_space),
+                decode_binary_internal(value, self.plus_as_space),
+            ));
+        }
+    }
+}
+
+/// Builds a query string from `key=value` pairs, percent-encoding them as they're appended.
+///
+/// By default it uses the `application/x-www-form-urlencoded` format (spaces are `+`),
+/// which is the format that [`parse_query`] expects.
+///
+/// ```rust
+/// use urlencoding::QueryBuilder;
+///
+/// let query = QueryBuilder::new()
+///     .append_pair("q", "1 + 1")
+///     .append_key_only("debug")
+///     .extend_pairs([("lang", "en"), ("page", "2")])
+///     .finish()
+///     .unwrap();
+/// assert_eq!(query, "q=1+%2B+1&debug&lang=en&page=2");
+/// ```
+///
+/// It can write to any [`fmt::Write`], such as an existing `String` or a `fmt::Formatter`.
+/// Nothing is written before the first pair, so add the `?` yourself.
+#[derive(Clone, Debug)]
+pub struct QueryBuilder<W = String> {
+    target: W,
+    opts: EncodeOptions,
+    has_pairs: bool,
+    result: fmt::Result,
+}
+
+impl QueryBuilder<String> {
+    /// Builds a new `String`
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_writer(String::new())
+    }
+}
+
+impl Default for QueryBuilder<String> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: fmt::Write> QueryBuilder<W> {
+    /// Appends pairs to the given writer, e.g. `&mut String`
+    #[inline]
+    #[must_use]
+    pub fn with_writer(target: W) -> Self {
+        Self {
+            target,
+            opts: EncodeOptions::FORM,
+            has_pairs: false,
+            result: Ok(()),
+        }
+    }
+
+    /// If `true` (the default), encodes the way HTML forms do, with spaces as `+`.
+    ///
+    /// If `false`, encodes spaces as `%20` and keeps characters allowed in RFC 3986 queries,
+    /// like [`encode_query_value`](crate::encode_query_value).
+    #[inline]
+    #[must_use]
+    pub fn form(mut self, yes: bool) -> Self {
+        let opts = if yes { EncodeOptions::FORM } else { EncodeOptions::with_set(EncodeSet::QUERY_VALUE) };
+        self.opts = opts.with_hex_case(self.opts.hex_case);
+        self
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Appends `key=value`
+    #[inline]
+    pub fn append_pair(&mut self, key: &str, value: &str) -> &mut Self {
+        self.append_key_only(key);
+        self.push_str("=");
+        self.push_encoded(value);
+        self
+    }
+
+    /// Appends `key` without `=`
+    #[inline]
+    pub fn append_key_only(&mut self, key: &str) -> &mut Self {
+        if self.has_pairs {
+            self.push_str("&");
+        }
+        self.has_pairs = true;
+        self.push_encoded(key);
+        self
+    }
+
+    /// Appends all `(key, value)` pairs from the iterator
+    pub fn extend_pairs<I, K, V>(&mut self, pairs: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (key, value) in pairs {
+            self.append_pair(key.as_ref(), value.as_ref());
+        }
+        self
+    }
+
+    /// Returns the writer, and starts a new query with an empty one. Fails if the writer has returned an error,
+    /// and then the partially written query is dropped.
+    ///
+    /// The result can't be an error when building a `String`.
+    #[inline]
+    pub fn finish(&mut self) -> Result<W, fmt::Error> where W: Default {
+        let target = core::mem::take(&mut self.target);
+        self.has_pairs = false;
+        core::mem::replace(&mut self.result, Ok(()))?;
+        Ok(target)
+    }
+
+    /// Same as [`finish`](Self::finish), but for writers that aren't `Default`.
+    #[inline]
+    pub fn into_inner(self) -> Result<W, fmt::Error> {
+        self.result?;
+        Ok(self.target)
+    }
+
+    #[inline]
+    fn push_str(&mut self, s: &str) {
+        if self.result.is_ok() {
+            self.result = self.target.write_str(s);
+        }
+    }
+
+    #[inline]
+    fn push_encoded(&mut self, data: &str) {
+        if self.result.is_ok() {
+            let target = &mut self.target;
+            self.result = encode_into(data.as_bytes(), false, &self.opts, |s| target.write_str(s)).map(drop);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::EncodeSet;
+
+/// Number of leading bytes that are not in the set, i.e. can be copied to the output unchanged.
+///
+/// Uses AVX2 or SSSE3 when available, and otherwise checks 8 bytes at a time.
+#[inline]
+pub(crate) fn unescaped_prefix_len(data: &[u8], set: &EncodeSet) -> usize {
+    // runs between escaped bytes are usually short, and not worth the setup
+    let head_len = scalar(&data[..data.len().min(16)], set);
+    if head_len < 16 {
+        return head_len;
+    }
+    16 + simd_or_words(&data[16..], set)
+}
+
+/// Kept out of line, so that the short runs don't pay for the feature detection
+#[inline(never)]
+fn simd_or_words(data: &[u8], set: &EncodeSet) -> usize {
+    #[cfg(all(target_arch = "x86_64", feature = "std"))]
+    {
+        if std::is_x86_feature_detected!("avx2") {
+            return unsafe { x86::avx2(data, &set.table) };
+        }
+        if std::is_x86_feature_detected!("ssse3") {
+            return unsafe { x86::ssse3(data, &set.table) };
+        }
+    }
+    #[cfg(all(target_arch = "x86_64", not(feature = "std"), target_feature = "ssse3"))]
+    {
+        return unsafe { x86::ssse3(data, &set.table) };
+    }
+
+    #[allow(unreachable_code)]
+    words(data, set)
+}
+
+#[inline]
+fn scalar(data: &[u8], set: &EncodeSet) -> usize {
+    data.iter().take_while(|&&c| !set.contains(c)).count()
+}
+
+/// Classifies 8 bytes at a time, by checking them against ranges of bytes that don't need escaping
+fn words(data: &[u8], set: &EncodeSet) -> usize {
+    // setting up the ranges takes about as long as looking up 32 bytes
+    let head = data.len().min(32);
+    let mut len = words_lookup(&data[..head], set);
+    if len < head || head == data.len() {
+        return len;
+    }
+    let Some(ranges) = SafeRanges::new(set) else {
+        return len + words_lookup(&data[len..], set);
+    };
+    for chunk in data[len..].chunks_exact(8) {
+        let escaped = ranges.escaped_mask(u64::from_le_bytes(chunk.try_into().unwrap()));
+        if escaped != 0 {
+            return len + (escaped.trailing_zeros() / 8) as usize;
+        }
+        len += 8;
+    }
+    len + scalar(&data[len..], set)
+}
+
+/// For sets that are too fragmented to check as ranges
+fn words_lookup(data: &[u8], set: &EncodeSet) -> usize {
+    let mut len = 0;
+    for chunk in data.chunks_exact(8) {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        // non-ASCII bytes are always escaped
+        if word & HI != 0 {
+            break;
+        }
+        let escaped = chunk.iter().fold(0, |acc, &c| acc | (set.table[(c & 15) as usize] >> (c >> 4)));
+        if escaped & 1 != 0 {
+            break;
+        }
+        len += 8;
+    }
+    len + scalar(&data[len..], set)
+}
+
+const LO: u64 = u64::from_ne_bytes([0x01; 8]);
+const HI: u64 = u64::from_ne_bytes([0x80; 8]);
+
+/// Runs of consecutive ASCII bytes that are not in the set, as constants for adding to every byte of a word
+struct SafeRanges {
+    /// `128 - first` and `127 - last` in every byte, so that the high bit of the sum tells which side of the bound the byte is
+    bounds: [(u64, u64); Self::MAX],
+}
+
+impl SafeRanges {
+    /// Every range is checked for every word, so more fragmented sets are looked up byte by byte instead. [`EncodeSet::PATH_SEGMENT`] needs 9.
+    const MAX: usize = 10;
+
+    fn new(set: &EncodeSet) -> Option<Self> {
+        let safe = !escaped_bits(set);
+        let mut firsts = safe & !(safe << 1);
+        let mut lasts = safe & !(safe >> 1);
+        if firsts.count_ones() as usize > Self::MAX {
+            return None;
+        }
+        // unused bounds never match, since they'd need the high bit already set
+        let mut bounds = [(0, 0); Self::MAX];
+        for bound in &mut bounds[..firsts.count_ones() as usize] {
+            let (first, last) = (firsts.trailing_zeros(), lasts.trailing_zeros());
+            *bound = (LO * u64::from(128 - first), LO * u64::from(127 - last));
+            firsts &= firsts - 1;
+            lasts &= lasts - 1;
+        }
+        Some(Self { bounds })
+    }
+
+    /// High bit of every byte that needs escaping, with false positives only after the first one
+    #[inline]
+    fn escaped_mask(&self, word: u64) -> u64 {
+        // non-ASCII bytes may carry into the next byte, but only after they have been marked as escaped
+        let mut safe = 0;
+        for &(from_first, from_last) in &self.bounds {
+            safe |= word.wrapping_add(from_first) & !word.wrapping_add(from_last);
+        }
+        (!safe | word) & HI
+    }
+}
+
+/// Bit `n` is set if ASCII byte `n` is in the set
+fn escaped_bits(set: &EncodeSet) -> u128 {
+    let [low, high] = [0, 8].map(|i| u64::from_le_bytes(set.table[i..i + 8].try_into().unwrap()));
+    let mut bits = 0;
+    for high_nibble in 0..8 {
+        // gathers bit `high_nibble` of every table entry into one byte, with the entry for low nibble `i` in bit `i`
+        let gather = |entries: u64| ((entries >> high_nibble) & LO).wrapping_mul(0x0102_0408_1020_4080) >> 56;
+        let row = gather(low) | (gather(high) << 8);
+        bits |= u128::from(row) << (high_nibble * 16);
+    }
+    bits
+}
+
+/// Number of leading bytes before the first `%` (or `+` if `plus_as_space`), i.e. the literal part to copy when decoding
+#[inline]
+pub(crate) fn literal_len(data: &[u8], plus_as_space: bool) -> usize {
+    let plus = if plus_as_space { b'+' } else { b'%' };
+    #[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+    {
+        // SSE2 is always available on x86-64
+        unsafe { find_sse2(data, plus) }
+    }
+    #[cfg(not(all(target_arch = "x86_64", target_feature = "sse2")))]
+    {
+        find_words(data, plus)
+    }
+}
+
+/// Finds `%` or `other`
+#[cfg(all(target_arch = "x86_64", target_feature = "sse2"))]
+#[inline]
+unsafe fn find_sse2(data: &[u8], other: u8) -> usize {
+    use core::arch::x86_64::*;
+
+    let percent = _mm_set1_epi8(b'%' as i8);
+    let other_bytes = _mm_set1_epi8(other as i8);
+    let mut len = 0;
+    while len + 16 <= data.len() {
+        let chunk = _mm_loadu_si128(data.as_ptr().add(len).cast());
+        let found = _mm_or_si128(_mm_cmpeq_epi8(chunk, percent), _mm_cmpeq_epi8(chunk, other_bytes));
+        let mask = _mm_movemask_epi8(found) as u32;
+        if mask != 0 {
+            return len + mask.trailing_zeros() as usize;
+        }
+        len += 16;
+    }
+    len + find_scalar(&data[len..], other)
+}
+
+#[inline]
+fn find_scalar(data: &[u8], other: u8) -> usize {
+    data.iter().take_while(|&&c| c != b'%' && c != other).count()
+}
+
+/// Finds `%` or `other` 8 bytes at a time, using the classic "has zero byte" trick
+#[cfg_attr(all(target_arch = "x86_64", target_feature = "sse2", not(test)), allow(dead_code))]
+fn find_words(data: &[u8], other: u8) -> usize {
+    let has_zero = |word: u64| word.wrapping_sub(LO) & !word & HI;
+
+    let mut len = 0;
+    for chunk in data.chunks_exact(8) {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        let found = has_zero(word ^ (LO * u64::from(b'%'))) | has_zero(word ^ (LO * u64::from(other)));
+        if found != 0 {
+            // false positives are only possible after a true match, so the lowest bit is exact
+            return len + (found.trailing_zeros() / 8) as usize;
+        }
+        len += 8;
+    }
+    len + find_scalar(&data[len..], other)
+}
+
+#[cfg(all(target_arch = "x86_64", any(feature = "std", test, target_feature = "ssse3")))]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    /// Bit `n` for high nibble `n` of ASCII bytes. Non-ASCII bytes get no bit, so they're never safe.
+    const HIGH_NIBBLE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    /// Looks up the low nibble of each byte in the table of safe high nibbles,
+    /// and returns a bit mask of bytes that need escaping.
+    #[inline(always)]
+    unsafe fn escaped_mask_128(chunk: __m128i, safe_table: __m128i, high_bits: __m128i) -> u32 {
+        let low_nibbles = _mm_and_si128(chunk, _mm_set1_epi8(0x0F));
+        let high_nibbles = _mm_and_si128(_mm_srli_epi16(chunk, 4), _mm_set1_epi8(0x0F));
+        let safe = _mm_and_si128(_mm_shuffle_epi8(safe_table, low_nibbles), _mm_shuffle_epi8(high_bits, high_nibbles));
+        _mm_movemask_epi8(_mm_cmpeq_epi8(safe, _mm_setzero_si128())) as u32
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn ssse3(data: &[u8], table: &[u8; 16]) -> usize {
+        let safe_table = _mm_xor_si128(_mm_loadu_si128(table.as_ptr().cast()), _mm_set1_epi8(-1));
+        let high_bits = _mm_loadu_si128(HIGH_NIBBLE_BITS.as_ptr().cast());
+        let mut len = 0;
+        while len + 16 <= data.len() {
+            let chunk = _mm_loadu_si128(data.as_ptr().add(len).cast());
+            let escaped = escaped_mask_128(chunk, safe_table, high_bits);
+            if escaped != 0 {
+                return len + escaped.trailing_zeros() as usize;
+            }
+            len += 16;
+        }
+        len + tail(&data[len..], table)
+    }
+
+    #[cfg(any(feature = "std", test))]
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn avx2(data: &[u8], table: &[u8; 16]) -> usize {
+        // the shuffle works within each 128-bit half, so the tables are repeated in both halves
+        let safe_table = _mm256_xor_si256(_mm256_broadcastsi128_si256(_mm_loadu_si128(table.as_ptr().cast())), _mm256_set1_epi8(-1));
+        let high_bits = _mm256_broadcastsi128_si256(_mm_loadu_si128(HIGH_NIBBLE_BITS.as_ptr().cast()));
+        let mut len = 0;
+        while len + 32 <= data.len() {
+            let chunk = _mm256_loadu_si256(data.as_ptr().add(len).cast());
+            let low_nibbles = _mm256_and_si256(chunk, _mm256_set1_epi8(0x0F));
+            let high_nibbles = _mm256_and_si256(_mm256_srli_epi16(chunk, 4), _mm256_set1_epi8(0x0F));
+            let safe = _mm256_and_si256(_mm256_shuffle_epi8(safe_table, low_nibbles), _mm256_shuffle_epi8(high_bits, high_nibbles));
+            let escaped = _mm256_movemask_epi8(_mm256_cmpeq_epi8(safe, _mm256_setzero_si256())) as u32;
+            if escaped != 0 {
+                return len + escaped.trailing_zeros() as usize;
+            }
+            len += 32;
+        }
+        if len + 16 <= data.len() {
+            let chunk = _mm_loadu_si128(data.as_ptr().add(len).cast());
+            let escaped = escaped_mask_128(chunk, _mm256_castsi256_si128(safe_table), _mm256_castsi256_si128(high_bits));
+            if escaped != 0 {
+                return len + escaped.trailing_zeros() as usize;
+            }
+            len += 16;
+        }
+        len + tail(&data[len..], table)
+    }
+
+    #[inline(always)]
+    fn tail(data: &[u8], table: &[u8; 16]) -> usize {
+        data.iter().take_while(|&&c| c < 128 && (table[(c & 15) as usize] >> (c >> 4)) & 1 == 0).count()
+    }
+}
+
+#[test]
+fn literal_len_matches_scalar() {
+    let mut data = [b'a'; 70];
+    for len in 0..data.len() {
+        for &byte in b"%+\x00\x25\x80\xa5\xff" {
+            for pos in 0..len {
+                data[..len].fill(b'a');
+                data[pos] = byte;
+                let data = &data[..len];
+                for (plus_as_space, other) in [(false, b'%'), (true, b'+')] {
+                    let expected = find_scalar(data, other);
+                    assert_eq!(literal_len(data, plus_as_space), expected);
+                    assert_eq!(find_words(data, other), expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn matches_scalar() {
+    // every other byte is too fragmented for ranges
+    let odd = (0..64).fold(EncodeSet::EMPTY, |set, i| set.add(i * 2 + 1));
+    let sets = [EncodeSet::DEFAULT, EncodeSet::EMPTY, EncodeSet::PATH, EncodeSet::WHATWG_FORM, EncodeSet::NON_ALPHANUMERIC.add(b'a'), odd];
+    let mut data = [b'a'; 100];
+    for set in sets {
+        for len in 0..data.len() {
+            for &byte in b"a%/ \x00\x7f\x80\xff~" {
+                for pos in 0..len.min(70) {
+                    data[..len].fill(b'a');
+                    data[pos] = byte;
+                    let data = &data[..len];
+                    let expected = scalar(data, &set);
+                    assert_eq!(unescaped_prefix_len(data, &set), expected);
+                    assert_eq!(words(data, &set), expected);
+                    assert_eq!(words_lookup(data, &set), expected);
+                    #[cfg(target_arch = "x86_64")]
+                    {
+                        if std::is_x86_feature_detected!("ssse3") {
+                            assert_eq!(unsafe { x86::ssse3(data, &set.table) }, expected);
+                        }
+                        if std::is_x86_feature_detected!("avx2") {
+                            assert_eq!(unsafe { x86::avx2(data, &set.table) }, expected);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn safe_ranges() {
+    for set in [EncodeSet::DEFAULT, EncodeSet::EMPTY, EncodeSet::CONTROLS, EncodeSet::PATH_SEGMENT, EncodeSet::WHATWG_FORM] {
+        let bits = escaped_bits(&set);
+        for byte in 0..128 {
+            assert_eq!(bits >> byte & 1 != 0, set.contains(byte), "{byte}");
+        }
+        let ranges = SafeRanges::new(&set).unwrap();
+        for byte in 0..=255u8 {
+            for pos in 0..8 {
+                let mut word = [b'a'; 8];
+                word[pos] = byte;
+                let escaped = ranges.escaped_mask(u64::from_le_bytes(word));
+                let expected = if set.contains(byte) { pos as u32 * 8 + 7 } else { 64 };
+                assert_eq!(escaped.trailing_zeros(), expected, "{byte}");
+            }
+        }
+    }
+    let used = |set| SafeRanges::new(&set).unwrap().bounds.iter().filter(|&&b| b != (0, 0)).count();
+    assert_eq!(used(EncodeSet::DEFAULT), 6);
+    assert_eq!(used(EncodeSet::PATH_SEGMENT), 9);
+    assert!(SafeRanges::new(&(0..64).fold(EncodeSet::EMPTY, |set, i| set.add(i * 2))).is_none());
+}
@@ -0,0 +1,829 @@
+//! Serde support for `application/x-www-form-urlencoded` query strings and form bodies.
+//!
+//! Requires the `serde` feature.
+//!
+//! ```rust
+//! #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+//! struct Search<'a> {
+//!     q: &'a str,
+//!     page: Option<u32>,
+//!     tag: Vec<String>,
+//! }
+//!
+//! let search: Search = urlencoding::serde::from_str("q=rust&tag=url&tag=web%20dev").unwrap();
+//! assert_eq!(search, Search { q: "rust", page: None, tag: vec!["url".into(), "web dev".into()] });
+//!
+//! assert_eq!(urlencoding::serde::to_string(&search).unwrap(), "q=rust&tag=url&tag=web+dev");
+//! ```
+//!
+//! Structs and maps are supported at the top level, as well as sequences of `(key, value)` pairs.
+//! Values can be strings, numbers, booleans, `Option`s (`None` is omitted), unit enum variants,
+//! and sequences of these, which are written as repeated keys.
+//!
+//! Fields of type `&str` borrow from the input, which only works if the value doesn't contain any escapes.
+//! Use `Cow<str>` with `#[serde(borrow)]` to borrow when possible.
+
+use crate::{parse_query, QueryBuilder};
+use ::serde::de::value::{MapDeserializer, SeqDeserializer};
+use ::serde::de::{self, Deserialize, DeserializeSeed, EnumAccess, IntoDeserializer, VariantAccess, Visitor};
+use ::serde::ser::{self, Impossible, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Error returned by [`to_string`] and [`from_str`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl Error {
+    fn unsupported(what: &str) -> Self {
+        Self(format!("{what} can't be urlencoded"))
+    }
+}
+
+/// Serializes a struct, a map, or a sequence of pairs as an `application/x-www-form-urlencoded` string
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, Error> {
+    let mut query = QueryBuilder::new();
+    value.serialize(PairsSerializer { query: &mut query })?;
+    query.into_inner().map_err(ser::Error::custom)
+}
+
+/// Deserializes an `application/x-www-form-urlencoded` string into a struct, a map, or a sequence of pairs.
+///
+/// `+` is decoded as a space, and invalid UTF-8 is replaced with `�`.
+pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, Error> {
+    T::deserialize(Deserializer { input })
+}
+
+struct PairsSerializer<'q> {
+    query: &'q mut QueryBuilder,
+}
+
+impl<'q> ser::Serializer for PairsSerializer<'q> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = MapSerializer<'q>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, _: bool) -> Result<(), Error> { Err(Error::unsupported("top-level bool")) }
+    fn serialize_i8(self, _: i8) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_i16(self, _: i16) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_i32(self, _: i32) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_i64(self, _: i64) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_u8(self, _: u8) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_u16(self, _: u16) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_u32(self, _: u32) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_u64(self, _: u64) -> Result<(), Error> { Err(Error::unsupported("top-level integer")) }
+    fn serialize_f32(self, _: f32) -> Result<(), Error> { Err(Error::unsupported("top-level float")) }
+    fn serialize_f64(self, _: f64) -> Result<(), Error> { Err(Error::unsupported("top-level float")) }
+    fn serialize_char(self, _: char) -> Result<(), Error> { Err(Error::unsupported("top-level char")) }
+    fn serialize_str(self, _: &str) -> Result<(), Error> { Err(Error::unsupported("top-level string")) }
+    fn serialize_bytes(self, _: &[u8]) -> Result<(), Error> { Err(Error::unsupported("top-level bytes")) }
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), Error> { Err(Error::unsupported("top-level enum")) }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str, _: &T) -> Result<(), Error> {
+        Err(Error::unsupported("top-level enum"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::unsupported("top-level tuple struct"))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::unsupported("top-level enum"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer<'q>, Error> {
+        Ok(MapSerializer { query: self.query, key: None })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::unsupported("top-level enum"))
+    }
+}
+
+impl ser::SerializeStruct for PairsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        value.serialize(ValueSerializer { key, query: self.query, in_seq: false })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Sequence of `(key, value)` pairs
+impl ser::SerializeSeq for PairsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, pair: &T) -> Result<(), Error> {
+        pair.serialize(PairSerializer { query: self.query, key: None })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for PairsSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, pair: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, pair)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct MapSerializer<'q> {
+    query: &'q mut QueryBuilder,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error::unsupported("map value without a key"))?;
+        value.serialize(ValueSerializer { key: &key, query: self.query, in_seq: false })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A `(key, value)` tuple
+struct PairSerializer<'q> {
+    query: &'q mut QueryBuilder,
+    key: Option<String>,
+}
+
+impl ser::SerializeTuple for PairSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        match self.key.take() {
+            None => {
+                self.key = Some(value.serialize(KeySerializer)?);
+                Ok(())
+            },
+            Some(key) => value.serialize(ValueSerializer { key: &key, query: self.query, in_seq: false }),
+        }
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+macro_rules! unsupported_pair {
+    ($($method:ident: $ty:ty),*) => {
+        $(fn $method(self, _: $ty) -> Result<(), Error> { Err(Error::unsupported("sequence element that isn't a pair")) })*
+    };
+}
+
+impl<'q> ser::Serializer for PairSerializer<'q> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    unsupported_pair!(serialize_bool: bool, serialize_i8: i8, serialize_i16: i16, serialize_i32: i32, serialize_i64: i64,
+        serialize_u8: u8, serialize_u16: u16, serialize_u32: u32, serialize_u64: u64, serialize_f32: f32, serialize_f64: f64,
+        serialize_char: char, serialize_str: &str, serialize_bytes: &[u8], serialize_unit_struct: &'static str);
+
+    fn serialize_none(self) -> Result<(), Error> { Err(Error::unsupported("sequence element that isn't a pair")) }
+    fn serialize_unit(self) -> Result<(), Error> { Err(Error::unsupported("sequence element that isn't a pair")) }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<(), Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str, _: &T) -> Result<(), Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, Error> {
+        if len == 2 {
+            Ok(self)
+        } else {
+            Err(Error::unsupported("sequence element that isn't a pair"))
+        }
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+
+    fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeStructVariant, Error> {
+        Err(Error::unsupported("sequence element that isn't a pair"))
+    }
+}
+
+/// Serializers of plain values implement `put()`
+macro_rules! serialize_plain_values {
+    () => {
+        fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> { self.put(if v { "true" } else { "false" }) }
+        fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> { self.put(&v.to_string()) }
+        fn serialize_char(self, v: char) -> Result<Self::Ok, Error> { self.put(v.encode_utf8(&mut [0; 4])) }
+        fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> { self.put(v) }
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Error> {
+            self.put(std::str::from_utf8(v).map_err(|_| Error::unsupported("non-UTF-8 bytes"))?)
+        }
+
+        fn serialize_unit_variant(self, _: &'static str, _: u32, variant: &'static str) -> Result<Self::Ok, Error> {
+            self.put(variant)
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, value: &T) -> Result<Self::Ok, Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _: &'static str, _: u32, _: &'static str, _: &T) -> Result<Self::Ok, Error> {
+            Err(Error::unsupported("enum with data"))
+        }
+
+        fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, Error> {
+            Err(Error::unsupported("tuple struct"))
+        }
+
+        fn serialize_tuple_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeTupleVariant, Error> {
+            Err(Error::unsupported("enum with data"))
+        }
+
+        fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+            Err(Error::unsupported("nested map"))
+        }
+
+        fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct, Error> {
+            Err(Error::unsupported("nested struct"))
+        }
+
+        fn serialize_struct_variant(self, _: &'static str, _: u32, _: &'static str, _: usize) -> Result<Self::SerializeStructVariant, Error> {
+            Err(Error::unsupported("enum with data"))
+        }
+    };
+}
+
+/// Map keys
+struct KeySerializer;
+
+impl KeySerializer {
+    fn put(self, key: &str) -> Result<String, Error> {
+        Ok(key.to_owned())
+    }
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    serialize_plain_values!();
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Err(Error::unsupported("optional key"))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<String, Error> {
+        Err(Error::unsupported("optional key"))
+    }
+
+    fn serialize_unit(self) -> Result<String, Error> {
+        Err(Error::unsupported("unit key"))
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<String, Error> {
+        Err(Error::unsupported("unit key"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(Error::unsupported("sequence key"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(Error::unsupported("tuple key"))
+    }
+}
+
+/// Value of a field. Sequences are written as repeated keys.
+struct ValueSerializer<'k, 'q> {
+    key: &'k str,
+    query: &'q mut QueryBuilder,
+    in_seq: bool,
+}
+
+impl ValueSerializer<'_, '_> {
+    fn put(self, value: &str) -> Result<(), Error> {
+        self.query.append_pair(self.key, value);
+        Ok(())
+    }
+}
+
+impl ser::Serializer for ValueSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    serialize_plain_values!();
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.put("")
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        self.put("")
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self, Error> {
+        if self.in_seq {
+            return Err(Error::unsupported("nested sequence"));
+        }
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, Error> {
+        self.serialize_seq(Some(len))
+    }
+}
+
+impl ser::SerializeSeq for ValueSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(ValueSerializer { key: self.key, query: self.query, in_seq: true })
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for ValueSerializer<'_, '_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+struct Deserializer<'de> {
+    input: &'de str,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Values of repeated keys are grouped together, in order of the first occurrence of each key
+    fn grouped_pairs(&self) -> Vec<(Part<'de>, Values<'de>)> {
+        let mut index = BTreeMap::<Cow<'de, str>, usize>::new();
+        let mut pairs: Vec<(Part<'de>, Values<'de>)> = Vec::new();
+        for (key, value) in parse_query(self.input) {
+            match index.get(&key) {
+                Some(&i) => pairs[i].1.0.push(value),
+                None => {
+                    index.insert(key.clone(), pairs.len());
+                    pairs.push((Part(key), Values(vec![value])));
+                },
+            }
+        }
+        pairs
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut map = MapDeserializer::new(self.grouped_pairs().into_iter());
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    /// Sequence of `(key, value)` pairs, in their original order
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut seq = MapDeserializer::new(parse_query(self.input).map(|(k, v)| (Part(k), Part(v))));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit_struct tuple tuple_struct enum identifier ignored_any
+    }
+}
+
+/// A single key or value
+struct Part<'de>(Cow<'de, str>);
+
+impl<'de> IntoDeserializer<'de, Error> for Part<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            visitor.$visit(self.0.parse().map_err(|e| Error(format!("{e}: {:?}", self.0)))?)
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Part<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _: &'static str, _: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    ::serde::forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> EnumAccess<'de> for Part<'de> {
+    type Error = Error;
+    type Variant = UnitOnly;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, UnitOnly), Error> {
+        Ok((seed.deserialize(self)?, UnitOnly))
+    }
+}
+
+/// Only unit variants can be expressed as a string
+struct UnitOnly;
+
+impl<'de> VariantAccess<'de> for UnitOnly {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, _: T) -> Result<T::Value, Error> {
+        Err(Error::unsupported("enum with data"))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, _: V) -> Result<V::Value, Error> {
+        Err(Error::unsupported("enum with data"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _: &'static [&'static str], _: V) -> Result<V::Value, Error> {
+        Err(Error::unsupported("enum with data"))
+    }
+}
+
+/// All values of a key. Sequences get all of them, other types get the last one.
+struct Values<'de>(Vec<Cow<'de, str>>);
+
+impl<'de> Values<'de> {
+    fn last(mut self) -> Part<'de> {
+        Part(self.0.pop().unwrap_or_default())
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Values<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_last {
+    ($($method:ident)*) => {
+        $(fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            de::Deserializer::$method(self.last(), visitor)
+        })*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Values<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.0.len() > 1 {
+            self.deserialize_seq(visitor)
+        } else {
+            self.last().deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut seq = SeqDeserializer::new(self.0.into_iter().map(Part));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _: &'static str, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.last().deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        self.last().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Error> {
+        self.last().deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        self.last().deserialize_struct(name, fields, visitor)
+    }
+
+    deserialize_last! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64
+        deserialize_char deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_unit deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Sort {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Query<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+        page: u32,
+        ratio: Option<f32>,
+        flag: bool,
+        sort: Sort,
+        tag: Vec<String>,
+        missing: Option<String>,
+        #[serde(default)]
+        defaulted: Vec<u8>,
+    }
+
+    #[test]
+    fn round_trip() {
+        let input = "name=joe&note=a+b%21&page=3&ratio=0.5&flag=true&sort=desc&tag=x&tag=y%26z";
+        let query: Query<'_> = from_str(input).unwrap();
+        assert_eq!(query, Query {
+            name: "joe",
+            note: "a b!".into(),
+            page: 3,
+            ratio: Some(0.5),
+            flag: true,
+            sort: Sort::Desc,
+            tag: vec!["x".into(), "y&z".into()],
+            missing: None,
+            defaulted: vec![],
+        });
+        assert!(matches!(query.note, Cow::Owned(_)));
+        assert_eq!(to_string(&query).unwrap(), input);
+
+        let query: Query<'_> = from_str("name=a&note=b&page=0&flag=false&sort=asc&tag=1&defaulted=7").unwrap();
+        assert!(matches!(query.note, Cow::Borrowed("b")));
+        assert_eq!(query.tag, ["1"]);
+        assert_eq!(query.defaulted, [7]);
+        assert_eq!(to_string(&query).unwrap(), "name=a&note=b&page=0&flag=false&sort=asc&tag=1&defaulted=7");
+    }
+
+    #[test]
+    fn errors() {
+        assert!(from_str::<Query<'_>>("name=a%20b&note=&page=0&flag=false&sort=asc").is_err());
+        assert!(from_str::<Query<'_>>("name=a&note=&page=x&flag=false&sort=asc").is_err());
+        assert!(from_str::<Query<'_>>("name=a&note=&page=1&flag=false&sort=other").is_err());
+        assert!(from_str::<Query<'_>>("name=a&note=&page=1&flag=false").is_err());
+        assert!(to_string(&5).is_err());
+        assert!(to_string(&[[1, 2, 3]]).is_err());
+        assert!(to_string(&[("a", [[1]])]).is_err());
+    }
+
+    #[test]
+    fn maps_and_pairs() {
+        let map: HashMap<String, String> = from_str("a=1&b=2&a=3").unwrap();
+        assert_eq!(map["a"], "3");
+        assert_eq!(map["b"], "2");
+
+        let pairs: Vec<(String, u8)> = from_str("a=1&b=2&a=3").unwrap();
+        assert_eq!(pairs, [("a".into(), 1), ("b".into(), 2), ("a".into(), 3)]);
+        assert_eq!(to_string(&pairs).unwrap(), "a=1&b=2&a=3");
+
+        let map: BTreeMap<u8, Vec<Option<char>>> = from_str("1=a&2=b&1=c").unwrap();
+        assert_eq!(to_string(&map).unwrap(), "1=a&1=c&2=b");
+        assert_eq!(to_string(&[("k y", "v&")]).unwrap(), "k+y=v%26");
+        assert_eq!(to_string(&()).unwrap(), "");
+    }
+}
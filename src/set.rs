@@ -0,0 +1,269 @@
+/// RFC 3986 `sub-delims`
+const SUB_DELIMS: &[u8] = b"!$&'()*+,;=";
+
+/// A set of ASCII bytes that will be percent-encoded.
+///
+/// Bytes that are not in the set are written out unchanged. Non-ASCII bytes (`0x80` and above)
+/// are always percent-encoded, regardless of the set, so that the output is always valid ASCII.
+///
+/// Sets are plain lookup tables that can be built in `const` context:
+///
+/// ```rust
+/// use urlencoding::{encode_with, EncodeSet};
+///
+/// // Like the default, but keeps `/` unescaped
+/// const PATH_LIKE: EncodeSet = EncodeSet::DEFAULT.remove(b'/');
+///
+/// assert_eq!(encode_with("a b/c", &PATH_LIKE), "a%20b/c");
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EncodeSet {
+    /// Bit `n >> 4` of `table[n & 15]` is set if ASCII byte `n` must be escaped.
+    ///
+    /// This layout allows checking 16 bytes at once with a SIMD byte shuffle.
+    pub(crate) table: [u8; 16],
+}
+
+impl EncodeSet {
+    /// Escapes nothing except non-ASCII bytes.
+    pub const EMPTY: Self = Self { table: [0; 16] };
+
+    /// Escapes ASCII control characters (`0x00`-`0x1F` and `0x7F`).
+    pub const CONTROLS: Self = Self::EMPTY.add_range(0x00, 0x1F).add(0x7F);
+
+    /// Escapes everything except ASCII letters and digits.
+    pub const NON_ALPHANUMERIC: Self = Self { table: [!0; 16] }
+        .remove_range(b'0', b'9')
+        .remove_range(b'A', b'Z')
+        .remove_range(b'a', b'z');
+
+    /// Escapes everything except alphanumerics and `-`, `_`, `.`, `~`. This is the set used by [`encode`](crate::encode).
+    pub const DEFAULT: Self = Self::NON_ALPHANUMERIC
+        .remove(b'-')
+        .remove(b'.')
+        .remove(b'_')
+        .remove(b'~');
+
+    /// Escapes everything except RFC 3986 `pchar`: unreserved characters, sub-delims, `:` and `@`.
+    ///
+    /// Suitable for a single path segment, since it escapes `/`. Used by [`encode_path_segment`](crate::encode_path_segment).
+    pub const PATH_SEGMENT: Self = Self::DEFAULT
+        .remove_all(SUB_DELIMS)
+        .remove(b':')
+        .remove(b'@');
+
+    /// Same as [`PATH_SEGMENT`](Self::PATH_SEGMENT), but keeps `/`. Used by [`encode_path`](crate::encode_path).
+    pub const PATH: Self = Self::PATH_SEGMENT.remove(b'/');
+
+    /// RFC 3986 `query` characters, except `&`, `=`, `+`, and `;`, which separate or encode
+    /// key-value pairs. Used by [`encode_query_value`](crate::encode_query_value).
+    pub const QUERY_VALUE: Self = Self::PATH
+        .remove(b'?')
+        .add_all(b"&=+;");
+
+    /// Escapes everything except RFC 3986 `fragment` characters (`pchar`, `/` and `?`). Used by [`encode_fragment`](crate::encode_fragment).
+    pub const FRAGMENT: Self = Self::PATH.remove(b'?');
+
+    /// Escapes everything except RFC 3986 `userinfo` characters, but also escapes `:`,
+    /// so that it can be used for the user name and the password separately. Used by [`encode_userinfo`](crate::encode_userinfo).
+    pub const USERINFO: Self = Self::DEFAULT.remove_all(SUB_DELIMS);
+
+    /// The [C0 control percent-encode set](https://url.spec.whatwg.org/#c0-control-percent-encode-set) from the WHATWG URL standard.
+    ///
+    /// Note that the WHATWG sets don't escape `%`, because they're meant for input that may already contain escapes.
+    pub const WHATWG_C0_CONTROL: Self = Self::CONTROLS;
+
+    /// The [fragment percent-encode set](https://url.spec.whatwg.org/#fragment-percent-encode-set) from the WHATWG URL standard.
+    pub const WHATWG_FRAGMENT: Self = Self::WHATWG_C0_CONTROL.add_all(b" \"<>`");
+
+    /// The [query percent-encode set](https://url.spec.whatwg.org/#query-percent-encode-set) from the WHATWG URL standard.
+    pub const WHATWG_QUERY: Self = Self::WHATWG_C0_CONTROL.add_all(b" \"#<>");
+
+    /// The [special-query percent-encode set](https://url.spec.whatwg.org/#special-query-percent-encode-set) from the WHATWG URL standard.
+    pub const WHATWG_SPECIAL_QUERY: Self = Self::WHATWG_QUERY.add(b'\'');
+
+    /// The [path percent-encode set](https://url.spec.whatwg.org/#path-percent-encode-set) from the WHATWG URL standard.
+    pub const WHATWG_PATH: Self = Self::WHATWG_QUERY.add_all(b"?^`{}");
+
+    /// The [userinfo percent-encode set](https://url.spec.whatwg.org/#userinfo-percent-encode-set) from the WHATWG URL standard.
+    pub const WHATWG_USERINFO: Self = Self::WHATWG_PATH
+        .add_all(b"/:;=@|")
+        .add_range(b'[', b'^');
+
+    /// The [component percent-encode set](https://url.spec.whatwg.org/#component-percent-encode-set) from the WHATWG URL standard.
+    ///
+    /// This is the set used by JavaScript's `encodeURIComponent()`.
+    pub const WHATWG_COMPONENT: Self = Self::WHATWG_USERINFO
+        .add_range(b'$', b'&')
+        .add_all(b"+,");
+
+    /// The [`application/x-www-form-urlencoded` percent-encode set](https://url.spec.whatwg.org/#application-x-www-form-urlencoded-percent-encode-set)
+    /// from the WHATWG URL standard.
+    pub const WHATWG_FORM: Self = Self::WHATWG_COMPONENT
+        .add_range(b'\'', b')')
+        .add_all(b"!~");
+
+    /// Returns a copy of the set that also escapes `byte`.
+    ///
+    /// Non-ASCII bytes are always escaped, so adding them has no effect.
+    #[inline]
+    #[must_use]
+    pub const fn add(mut self, byte: u8) -> Self {
+        if byte < 128 {
+            self.table[(byte & 15) as usize] |= 1 << (byte >> 4);
+        }
+        self
+    }
+
+    /// Returns a copy of the set that leaves `byte` unescaped.
+    ///
+    /// Non-ASCII bytes are always escaped, so removing them has no effect.
+    #[inline]
+    #[must_use]
+    pub const fn remove(mut self, byte: u8) -> Self {
+        if byte < 128 {
+            self.table[(byte & 15) as usize] &= !(1 << (byte >> 4));
+        }
+        self
+    }
+
+    /// Bytes escaped by either set
+    #[inline]
+    #[must_use]
+    pub const fn union(mut self, other: Self) -> Self {
+        let mut i = 0;
+        while i < 16 {
+            self.table[i] |= other.table[i];
+            i += 1;
+        }
+        self
+    }
+
+    /// Bytes escaped by this set, but not by the `other` set
+    #[inline]
+    #[must_use]
+    pub const fn difference(mut self, other: Self) -> Self {
+        let mut i = 0;
+        while i < 16 {
+            self.table[i] &= !other.table[i];
+            i += 1;
+        }
+        self
+    }
+
+    /// Whether `byte` will be percent-encoded
+    #[inline(always)]
+    #[must_use]
+    pub const fn contains(&self, byte: u8) -> bool {
+        byte >= 128 || (self.table[(byte & 15) as usize] >> (byte >> 4)) & 1 != 0
+    }
+
+    const fn add_all(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self = self.add(bytes[i]);
+            i += 1;
+        }
+        self
+    }
+
+    const fn remove_all(mut self, bytes: &[u8]) -> Self {
+        let mut i = 0;
+        while i < bytes.len() {
+            self = self.remove(bytes[i]);
+            i += 1;
+        }
+        self
+    }
+
+    const fn add_range(mut self, first: u8, last: u8) -> Self {
+        let mut byte = first;
+        while byte <= last {
+            self = self.add(byte);
+            byte += 1;
+        }
+        self
+    }
+
+    const fn remove_range(mut self, first: u8, last: u8) -> Self {
+        let mut byte = first;
+        while byte <= last {
+            self = self.remove(byte);
+            byte += 1;
+        }
+        self
+    }
+}
+
+impl Default for EncodeSet {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[test]
+fn set_algebra() {
+    assert!(EncodeSet::EMPTY.contains(0x80));
+    assert!(EncodeSet::EMPTY.remove(0xFF).contains(0xFF));
+    assert!(!EncodeSet::EMPTY.contains(0x7F));
+    assert!(EncodeSet::CONTROLS.contains(0x7F));
+    assert!(EncodeSet::CONTROLS.contains(0));
+    assert!(!EncodeSet::CONTROLS.contains(b' '));
+
+    for b in 0..=255u8 {
+        let safe = b.is_ascii_alphanumeric() || b"-._~".contains(&b);
+        assert_eq!(!safe, EncodeSet::DEFAULT.contains(b), "{b}");
+        assert_eq!(!b.is_ascii_alphanumeric(), EncodeSet::NON_ALPHANUMERIC.contains(b), "{b}");
+    }
+
+    let a = EncodeSet::EMPTY.add(b'a').add(b'b');
+    let b = EncodeSet::EMPTY.add(b'b').add(b'c');
+    assert_eq!(a.union(b), EncodeSet::EMPTY.add(b'a').add(b'b').add(b'c'));
+    assert_eq!(a.difference(b), EncodeSet::EMPTY.add(b'a'));
+    assert_eq!(a.remove(b'a'), EncodeSet::EMPTY.add(b'b'));
+}
+
+#[cfg(test)]
+fn assert_escapes_exactly(set: EncodeSet, escaped: impl Fn(u8) -> bool) {
+    for b in 0..=255u8 {
+        assert_eq!(escaped(b), set.contains(b), "{:?} 0x{b:02X}", b as char);
+    }
+}
+
+#[test]
+fn rfc3986_sets() {
+    let unreserved = |b: u8| b.is_ascii_alphanumeric() || b"-._~".contains(&b);
+    let pchar = |b: u8| unreserved(b) || SUB_DELIMS.contains(&b) || b":@".contains(&b);
+
+    assert_escapes_exactly(EncodeSet::PATH_SEGMENT, |b| !pchar(b));
+    assert_escapes_exactly(EncodeSet::PATH, |b| !pchar(b) && b != b'/');
+    assert_escapes_exactly(EncodeSet::FRAGMENT, |b| !pchar(b) && !b"/?".contains(&b));
+    assert_escapes_exactly(EncodeSet::QUERY_VALUE, |b| (!pchar(b) && !b"/?".contains(&b)) || b"&=+;".contains(&b));
+    assert_escapes_exactly(EncodeSet::USERINFO, |b| !unreserved(b) && !SUB_DELIMS.contains(&b));
+}
+
+#[test]
+fn whatwg_sets() {
+    // https://url.spec.whatwg.org/#percent-encoded-bytes
+    let c0 = |b: u8| !(0x20..=0x7E).contains(&b);
+    let fragment = |b: u8| c0(b) || b" \"<>`".contains(&b);
+    let query = |b: u8| c0(b) || b" \"#<>".contains(&b);
+    let special_query = |b: u8| query(b) || b == b'\'';
+    let path = |b: u8| query(b) || b"?^`{}".contains(&b);
+    let userinfo = |b: u8| path(b) || b"/:;=@[\\]^|".contains(&b);
+    let component = |b: u8| userinfo(b) || b"$%&+,".contains(&b);
+    let form = |b: u8| component(b) || b"!'()~".contains(&b);
+
+    assert_escapes_exactly(EncodeSet::WHATWG_C0_CONTROL, c0);
+    assert_escapes_exactly(EncodeSet::WHATWG_FRAGMENT, fragment);
+    assert_escapes_exactly(EncodeSet::WHATWG_QUERY, query);
+    assert_escapes_exactly(EncodeSet::WHATWG_SPECIAL_QUERY, special_query);
+    assert_escapes_exactly(EncodeSet::WHATWG_PATH, path);
+    assert_escapes_exactly(EncodeSet::WHATWG_USERINFO, userinfo);
+    assert_escapes_exactly(EncodeSet::WHATWG_COMPONENT, component);
+    assert_escapes_exactly(EncodeSet::WHATWG_FORM, form);
+
+    // the form set leaves only these unescaped
+    assert_escapes_exactly(EncodeSet::WHATWG_FORM, |b| !(b.is_ascii_alphanumeric() || b"*-._".contains(&b)));
+}
@@ -0,0 +1,347 @@
+use crate::enc::{encode_into, EncodeOptions};
+use crate::{Decoder, EncodeSet, HexCase};
+use std::convert::Infallible;
+use std::io;
+
+/// Size of the output buffer
+pub(crate) const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Percent-encodes everything written to it, and writes the result to the inner writer.
+///
+/// The encoded output is buffered, so there's no need to wrap it in a `BufWriter`.
+/// Like `BufWriter`, it flushes when dropped, but errors are ignored then, so call [`flush`](io::Write::flush)
+/// or [`into_inner`](Self::into_inner) to check them.
+///
+/// ```rust
+/// use std::io::Write;
+/// use urlencoding::EncodingWriter;
+///
+/// let mut writer = EncodingWriter::new(Vec::new());
+/// writer.write_all(b"hello ")?;
+/// writer.write_all("wörld".as_bytes())?;
+/// assert_eq!(writer.into_inner()?, b"hello%20w%C3%B6rld");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct EncodingWriter<W: io::Write> {
+    /// Always `Some`, except in `into_inner`
+    inner: Option<W>,
+    buf: Vec<u8>,
+    opts: EncodeOptions,
+}
+
+impl<W: io::Write> EncodingWriter<W> {
+    /// Percent-encodes every byte except alphanumerics and `-`, `_`, `.`, `~`, like [`encode`](crate::encode)
+    #[inline]
+    pub fn new(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::DEFAULT)
+    }
+
+    /// Percent-encodes bytes in the given set, like [`encode_with`](crate::encode_with)
+    #[inline]
+    pub fn with_set(inner: W, set: EncodeSet) -> Self {
+        Self::with_options(inner, EncodeOptions::with_set(set))
+    }
+
+    /// Encodes as `application/x-www-form-urlencoded`, like [`encode_form`](crate::encode_form)
+    #[inline]
+    pub fn form(inner: W) -> Self {
+        Self::with_options(inner, EncodeOptions::FORM)
+    }
+
+    /// Sets the letter case of the hex digits, which are uppercase by default
+    #[inline]
+    #[must_use]
+    pub fn hex_case(mut self, hex_case: HexCase) -> Self {
+        self.opts.hex_case = hex_case;
+        self
+    }
+
+    /// Percent-encodes with the given [`EncodeOptions`]
+    #[inline]
+    pub fn with_options(inner: W, opts: EncodeOptions) -> Self {
+        Self {
+            inner: Some(inner),
+            buf: Vec::with_capacity(BUFFER_SIZE),
+            opts,
+        }
+    }
+
+    /// The inner writer
+    #[inline]
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// The inner writer. Writing to it directly will mix unencoded data with the buffered encoded data.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Writes out the buffered data, and returns the inner writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush_buf()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    fn flush_buf(&mut self) -> io::Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+        let mut written = 0;
+        let res = loop {
+            if written >= self.buf.len() {
+                break Ok(());
+            }
+            match inner.write(&self.buf[written..]) {
+                Ok(0) => break Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => break Err(e),
+            }
+        };
+        self.buf.drain(..written);
+        res
+    }
+}
+
+impl<W: io::Write> io::Write for EncodingWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+        // every byte can become 3 bytes, and chunks are sized to always fit in the buffer
+        for chunk in data.chunks(BUFFER_SIZE / 3) {
+            if self.buf.len() + chunk.len() * 3 > BUFFER_SIZE {
+                if let Err(e) = self.flush_buf() {
+                    return if consumed > 0 { Ok(consumed) } else { Err(e) };
+                }
+            }
+            let buf = &mut self.buf;
+            let _ = encode_into(chunk, false, &self.opts, |s| {
+                buf.extend_from_slice(s.as_bytes());
+                Ok::<_, Infallible>(())
+            });
+            consumed += chunk.len();
+        }
+        Ok(consumed)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_buf()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: io::Write> Drop for EncodingWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.flush_buf();
+        }
+    }
+}
+
+/// Decodes percent-encoded data read from the inner reader.
+///
+/// Gives exactly the same result as [`decode_binary`](crate::decode_binary), including handling of
+/// incomplete escapes, even if they're split across reads.
+///
+/// The input is buffered, so there's no need to wrap the inner reader in a `BufReader`.
+///
+/// ```rust
+/// use std::io::Read;
+/// use urlencoding::DecodingReader;
+///
+/// let mut decoded = String::new();
+/// DecodingReader::new(&b"hello%20w%C3%B6rld"[..]).read_to_string(&mut decoded)?;
+/// assert_eq!(decoded, "hello wörld");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct DecodingReader<R: io::Read> {
+    inner: R,
+    decoder: Decoder,
+    input: Box<[u8]>,
+    decoded: Vec<u8>,
+    /// Start of unread data in `decoded`
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: io::Read> DecodingReader<R> {
+    /// Decodes like [`decode_binary`](crate::decode_binary)
+    #[inline]
+    pub fn new(inner: R) -> Self {
+        Self::with_decoder(inner, Decoder::new())
+    }
+
+    /// Decodes `application/x-www-form-urlencoded` data like [`decode_form_binary`](crate::decode_form_binary), with `+` as a space
+    #[inline]
+    pub fn form(inner: R) -> Self {
+        Self::with_decoder(inner, Decoder::form())
+    }
+
+    #[inline]
+    fn with_decoder(inner: R, decoder: Decoder) -> Self {
+        Self {
+            inner,
+            decoder,
+            input: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            decoded: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// The inner reader
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// The inner reader. Reading from it directly will skip data that has been buffered.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the inner reader. Data that has been buffered, but not read yet, is lost.
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Reads and decodes more data, after all previously decoded data has been read
+    fn fill_buf(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.pos = 0;
+        let n = self.inner.read(&mut self.input)?;
+        if n == 0 {
+            self.eof = true;
+            self.decoder.finish(&mut self.decoded);
+        } else {
+            self.decoder.feed(&self.input[..n], &mut self.decoded);
+        }
+        Ok(())
+    }
+}
+
+impl<R: io::Read> io::Read for DecodingReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        // an incomplete escape decodes to nothing until the next chunk arrives
+        while self.pos >= self.decoded.len() {
+            if self.eof {
+                return Ok(0);
+            }
+            self.fill_buf()?;
+        }
+        let n = out.len().min(self.decoded.len() - self.pos);
+        out[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{decode_binary, decode_form_binary, encode_binary, encode_form, encode_with};
+    use crate::trickle::Trickle;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn writer_matches_encode() {
+        let data: Vec<u8> = (0..100_000u32).map(|n| (n * 7 % 251) as u8).collect();
+        let mut w = EncodingWriter::new(Vec::new());
+        for chunk in data.chunks(777) {
+            w.write_all(chunk).unwrap();
+        }
+        assert_eq!(w.into_inner().unwrap(), encode_binary(&data).as_bytes());
+
+        let mut w = EncodingWriter::form(Vec::new());
+        write!(w, "a b+c").unwrap();
+        assert_eq!(w.into_inner().unwrap(), encode_form("a b+c").as_bytes());
+
+        let set = EncodeSet::PATH;
+        let mut w = EncodingWriter::with_set(Vec::new(), set);
+        write!(w, "/a b/").unwrap();
+        w.flush().unwrap();
+        assert_eq!(w.get_ref(), encode_with("/a b/", &set).as_bytes());
+    }
+
+    #[test]
+    fn writer_flushes_on_drop() {
+        let mut out = Vec::new();
+        {
+            let mut w = EncodingWriter::new(&mut out);
+            w.write_all(b"drop me").unwrap();
+            assert!(w.get_ref().is_empty());
+        }
+        assert_eq!(out, b"drop%20me");
+    }
+
+    /// Accepts 5 bytes at a time, and fails every other call
+    struct Flaky(Vec<u8>, bool);
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1 = !self.1;
+            if self.1 {
+                return Err(io::ErrorKind::Other.into());
+            }
+            let n = buf.len().min(5);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_retries_after_errors() {
+        let data = vec![b' '; BUFFER_SIZE];
+        let mut w = EncodingWriter::new(Flaky(Vec::new(), false));
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            if let Ok(n) = w.write(rest) {
+                rest = &rest[n..];
+            }
+        }
+        while w.flush().is_err() {}
+        assert_eq!(w.into_inner().unwrap().0, encode_binary(&data).as_bytes());
+    }
+
+    #[test]
+    fn reader_matches_decode_binary() {
+        let samples: [&[u8]; 12] = [
+            b"", b"%", b"%%", b"%2", b"%2%", b"%%20", b"%zz%2x%20", b"a+b%2B+", b"%F0%9F%91%BE%20Exterminate%21",
+            b"this%20that%", b"this%20that%2", b"%25%s%1G",
+        ];
+        for sample in samples {
+            for read_size in 1..6 {
+                let mut out = Vec::new();
+                let mut reader = DecodingReader::new(Trickle::new(sample));
+                let mut buf = [0; 8];
+                loop {
+                    let n = reader.read(&mut buf[..read_size]).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    out.extend_from_slice(&buf[..n]);
+                }
+                assert_eq!(out, &*decode_binary(sample));
+
+                let mut out = Vec::new();
+                DecodingReader::form(Trickle::new(sample)).read_to_end(&mut out).unwrap();
+                assert_eq!(out, &*decode_form_binary(sample));
+            }
+        }
+
+        let large: Vec<u8> = (0..50_000u32).flat_map(|n| if n % 3 == 0 { *b"%4" } else { *b"1%" }).collect();
+        let mut out = Vec::new();
+        DecodingReader::new(&large[..]).read_to_end(&mut out).unwrap();
+        assert_eq!(out, &*decode_binary(&large));
+    }
+}
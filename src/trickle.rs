@@ -0,0 +1,65 @@
+//! A slow reader and writer shared by the `std::io` and async I/O tests
+
+use std::io;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+use std::task::Poll;
+
+/// Reads or writes 1 to 5 bytes at a time, cycling through sizes.
+/// The async impls also return `Pending` every other time.
+pub(crate) struct Trickle<T> {
+    pub data: T,
+    calls: usize,
+}
+
+impl<T> Trickle<T> {
+    pub fn new(data: T) -> Self {
+        Self { data, calls: 0 }
+    }
+
+    /// Counts the call, and tells every other one to return `Pending`
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub fn is_pending(&mut self) -> bool {
+        self.calls += 1;
+        self.calls % 2 == 1
+    }
+
+    fn chunk_len(&self, max: usize) -> usize {
+        max.min(self.calls % 5 + 1)
+    }
+}
+
+impl Trickle<&[u8]> {
+    fn read_some(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.chunk_len(buf.len()).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        n
+    }
+
+    #[cfg(any(feature = "tokio", feature = "futures-io"))]
+    pub fn poll_read_some(&mut self, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if self.is_pending() {
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(self.read_some(buf)))
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+impl Trickle<Vec<u8>> {
+    pub fn poll_write_some(&mut self, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if self.is_pending() {
+            return Poll::Pending;
+        }
+        let n = self.chunk_len(buf.len());
+        self.data.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl io::Read for Trickle<&[u8]> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls += 1;
+        Ok(self.read_some(buf))
+    }
+}